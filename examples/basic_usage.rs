@@ -1,18 +1,45 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use ext_crud_rs::prelude::*;
+use futures_util::TryStreamExt;
 use serde::{Deserialize, Serialize};
-use tokio;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud, PartialEntity)]
 #[table_name("users")]
+#[has_many(Order, foreign_key = "user_id")]
+#[hooks]
 struct User {
     id: Uuid,
+    #[validate(length(min = 1, max = 255))]
     name: String,
+    #[sensitive]
+    #[validate(email)]
     email: String,
+    #[validate(range(min = 0, max = 150))]
     age: u32,
 }
 
+// 保存前にメールアドレスを正規化する
+#[async_trait]
+impl<C: Client> CrudHooks<C> for User {
+    async fn before_create(&mut self, _client: &C) -> Result<()> {
+        self.email = self.email.trim().to_lowercase();
+        Ok(())
+    }
+
+    async fn before_update(&mut self, client: &C) -> Result<()> {
+        CrudHooks::<C>::before_create(self, client).await
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ExtendedView)]
+#[view_of(User)]
+struct UserName {
+    id: Uuid,
+    name: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud, PartialEntity)]
 #[table_name("products")]
 #[partial_entity_name("ProductUpdate")]
@@ -20,8 +47,42 @@ struct Product {
     #[primary_key("product_id")]
     #[serde(rename = "product_id")] // TODO support only use primary_key(auto rename)
     product_code: String,
+    #[validate(length(min = 1), custom = "printable")]
     name: String,
+    #[validate(range(min = 0))]
     price: f64,
+    #[version]
+    version: i64,
+}
+
+fn printable(value: &str) -> Result<(), String> {
+    if value.chars().any(char::is_control) {
+        return Err("must not contain control characters".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud, PartialEntity)]
+#[table_name("orders")]
+#[belongs_to(User, key = "user_id")]
+struct Order {
+    id: Uuid,
+    user_id: Uuid,
+    quantity: u32,
+    #[created_at]
+    created_at: Option<DateTime<Utc>>,
+    #[updated_at]
+    updated_at: Option<DateTime<Utc>>,
+    #[soft_delete]
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ExtendedView)]
+#[view_of(Product)]
+struct ProductName {
+    #[serde(rename = "product_id")]
+    product_code: String,
+    name: String,
 }
 
 #[tokio::main]
@@ -31,7 +92,18 @@ async fn main() -> Result<()> {
     let supabase_service_role_key = std::env::var("SUPABASE_SERVICE_ROLE_KEY").map_err(|_| {
         anyhow::anyhow!("Please set the SUPABASE_SERVICE_ROLE_KEY environment variable")
     })?;
-    let client = SupabaseClient::new(supabase_api_url, &supabase_service_role_key);
+    let supabase = SupabaseClient::builder(supabase_api_url, &supabase_service_role_key).build()?;
+    // users の変更履歴を audit_log テーブルに記録する
+    let audit = AuditLayer::new(
+        TableSink::new(supabase.clone(), "audit_log"),
+        AuditConfig::new()
+            .entity::<SupabaseClient, User>()
+            .actor("basic_usage"),
+    );
+    let client = ClientBuilder::new(supabase.clone())
+        .layer(RetryLayer::default())
+        .layer(audit)
+        .build();
 
     // User の例（デフォルトの Partial 名を使用）
     let mut user = User {
@@ -45,32 +117,104 @@ async fn main() -> Result<()> {
     let crated_user = User::read(&client, user.id).await?;
     println!("Crated User: {:?}", crated_user);
 
+    let user_name = UserName::read(&client, user.id).await?;
+    println!("User Name: {:?}", user_name);
+
     user.age = 40;
     user.update(&client).await?;
 
     let updated_user = User::read(&client, user.id).await?;
     println!("Updated User: {:?}", updated_user);
 
+    // Order の例（リレーション）
+    let order = Order {
+        id: Uuid::new_v4(),
+        user_id: user.id,
+        quantity: 2,
+        created_at: None,
+        updated_at: None,
+        deleted_at: None,
+    };
+    // created_at / updated_at は create 時に自動で設定される
+    order.clone().create(&client).await?;
+    println!("Order User: {:?}", order.user(&client).await?);
+    println!("User Orders: {:?}", user.orders(&client).await?);
+    let users_with_orders = User::with_orders(vec![user.clone()], &client).await?;
+    println!("Users with Orders: {:?}", users_with_orders);
+
+    // 論理削除（deleted_at を設定し、読み込み時は除外される）
+    order.clone().delete(&client).await?;
+    println!(
+        "User Orders after delete: {:?}",
+        user.orders(&client).await?
+    );
+    let deleted_order = Order::read_with_deleted(&client, order.id).await?;
+    println!("Deleted Order: {:?}", deleted_order);
+    Order::restore(&client, order.id).await?;
+
+    // Postgres 関数の呼び出し（RPC）
+    let args = serde_json::json!({ "min_age": 18 });
+    let count: i64 = supabase.rpc("count_users_older_than", &args).await?;
+    println!("Users older than 18: {}", count);
+    let count: i64 = supabase
+        .rpc_with("count_users_older_than", &args, RpcOptions::get())
+        .await?;
+    println!("Users older than 18 (GET): {}", count);
+
+    // 全ユーザーを 1000 件ずつページングして読み出す
+    let exported = User::stream(&client, 1000)
+        .try_fold(0, |count, _user| async move { Ok(count + 1) })
+        .await?;
+    println!("Exported Users: {}", exported);
+
+    // 型付きカラム定数で絞り込みと並び替えを行う
+    let seniors = User::query()
+        .filter(User::AGE.gt(30u32))
+        .order(User::NAME.asc())
+        .limit(10)
+        .fetch(&client)
+        .await?;
+    println!("Users older than 30: {:?}", seniors);
+
     // Product の例（カスタム Partial 名を使用）
     let mut product = Product {
         product_code: Uuid::new_v4().to_string(), //"PROD-001".to_string(),
         name: "Super Widget".to_string(),
         price: 19.99,
+        version: 0,
     };
 
     product.clone().create(&client).await?;
     let created_product = Product::read(&client, product.product_code.clone()).await?;
     println!("Created Product: {:?}", created_product);
+    let product_name = ProductName::read(&client, product.product_code.clone()).await?;
+    println!("Product Name: {:?}", product_name);
 
     product.price = 24.99;
-    product.update(&client).await?;
+    product.update_versioned(&client).await?;
     let updated_product = Product::read(&client, product.product_code.clone()).await?;
     println!("Updated Product: {:?}", updated_product);
 
+    // 読み込み後に他で更新された行は上書きされない（楽観的排他制御）
+    let mut stale_product = created_product.clone();
+    stale_product.price = 0.0;
+    let error = stale_product.update(&client).await.unwrap_err();
+    println!("Stale Update: {:?}", error.downcast_ref::<Conflict>());
+
+    // 複数エンティティをまとめて更新（トランザクション）
+    client
+        .transaction(|tx| {
+            let mut user = user.clone();
+            user.age = 41;
+            tx.update(&user)?;
+            tx.update(&product)
+        })
+        .await?;
+
     let mut partial_product = product.to_partial();
     partial_product.price = Some(26.99);
     println!("Partial Product: {:?}", partial_product);
-    let applied_product = partial_product.apply_to(&updated_product);
+    let mut applied_product = partial_product.apply_to(&updated_product);
     println!("Applied Product: {:?}", applied_product);
     applied_product.update(&client).await?;
     let updated_applied_product = Product::read(&client, product.product_code.clone()).await?;
@@ -107,6 +251,35 @@ fn test_partial_no_specific_name() {
     assert_eq!(user.id, updated.id);
 }
 
+#[test]
+fn test_view_columns() {
+    assert_eq!(
+        <UserName as ExtendedView<SupabaseClient>>::COLUMNS,
+        ["id", "name"]
+    );
+    assert_eq!(
+        <ProductName as ExtendedView<SupabaseClient>>::COLUMNS,
+        ["product_id", "name"]
+    );
+}
+
+#[test]
+fn test_column_constants() {
+    assert_eq!(User::AGE.name(), "age");
+    assert_eq!(Product::PRODUCT_CODE.name(), "product_id");
+
+    let filter = User::AGE.gt(30u32);
+    assert_eq!(
+        (filter.column, filter.op, filter.value),
+        ("age", FilterOp::Gt, serde_json::json!(30))
+    );
+    let order = User::NAME.desc();
+    assert_eq!((order.column, order.ascending), ("name", false));
+    let _query = <User as ExtendedCrud<SupabaseClient>>::query()
+        .filter(User::EMAIL.is_in(["a@example.com", "b@example.com"]))
+        .order(User::NAME.asc());
+}
+
 #[test]
 fn test_partial_specific_name() {
     let code_or_id = "PROD-001";
@@ -114,6 +287,7 @@ fn test_partial_specific_name() {
         product_code: code_or_id.to_string(),
         name: "John Doe Product".to_string(),
         price: 30.0,
+        version: 0,
     };
     assert_eq!(*product.primary_key(), code_or_id.to_string());
 
@@ -126,3 +300,277 @@ fn test_partial_specific_name() {
     let updated = partial_product.apply_to(&product);
     assert_eq!(40.0, updated.price);
 }
+
+#[test]
+fn test_transaction_operations() {
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .build()
+        .unwrap();
+    let product = Product {
+        product_code: "PROD-001".to_string(),
+        name: "Super Widget".to_string(),
+        price: 19.99,
+        version: 0,
+    };
+    let mut tx = client.begin();
+    tx.update(&product).unwrap();
+    tx.delete::<Product>(&product.product_code).unwrap();
+    assert_eq!(
+        tx.operations(),
+        [
+            Operation::Update {
+                table: "products".to_string(),
+                key: "product_id".to_string(),
+                id: serde_json::json!("PROD-001"),
+                item: serde_json::json!({
+                    "product_id": "PROD-001",
+                    "name": "Super Widget",
+                    "price": 19.99,
+                    "version": 1,
+                }),
+                expected_version: Some(ExpectedVersion {
+                    column: "version".to_string(),
+                    version: 0,
+                }),
+            },
+            Operation::Delete {
+                table: "products".to_string(),
+                key: "product_id".to_string(),
+                id: serde_json::json!("PROD-001"),
+            },
+        ]
+    );
+    tx.rollback();
+}
+
+#[test]
+fn test_client_builder() {
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .schema("private")
+        .header("x-tenant-id", "tenant-1")
+        .timeout(std::time::Duration::from_secs(10))
+        .user_agent("ext-crud-rs-example")
+        .build();
+    assert!(client.is_ok());
+
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .header("x-tenant-id", "invalid\nvalue")
+        .build();
+    assert!(client.is_err());
+}
+
+#[test]
+fn test_with_user_token() {
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .build()
+        .unwrap();
+    assert!(client.with_user_token("user.access.token").is_ok());
+    assert!(client.with_user_token("invalid\ntoken").is_err());
+}
+
+#[test]
+fn test_sensitive_fields_redacted() {
+    let user = User {
+        id: Uuid::new_v4(),
+        name: "John Doe".to_string(),
+        email: "john@example.com".to_string(),
+        age: 30,
+    };
+    let fields = <User as ExtendedCrud<SupabaseClient>>::SENSITIVE_FIELDS;
+    assert_eq!(fields, ["email"]);
+    let redacted = redact(&serde_json::to_value(&user).unwrap(), fields);
+    assert_eq!(redacted["email"], "[REDACTED]");
+    assert_eq!(redacted["name"], "John Doe");
+}
+
+#[test]
+fn test_version_column() {
+    let mut product = Product {
+        product_code: "PROD-001".to_string(),
+        name: "Super Widget".to_string(),
+        price: 19.99,
+        version: 3,
+    };
+    assert_eq!(
+        <Product as ExtendedCrud<SupabaseClient>>::VERSION_NAME,
+        Some("version")
+    );
+    assert_eq!(
+        <Product as ExtendedCrud<SupabaseClient>>::version(&product),
+        Some(3)
+    );
+    <Product as ExtendedCrud<SupabaseClient>>::set_version(&mut product, 4);
+    assert_eq!(product.version, 4);
+    assert_eq!(<User as ExtendedCrud<SupabaseClient>>::VERSION_NAME, None);
+}
+
+#[test]
+fn test_timestamps() {
+    use chrono::TimeZone;
+
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 9, 0, 0).unwrap();
+    let clock = std::sync::Arc::new(FixedClock::new(now));
+    let client = ClientBuilder::new(
+        SupabaseClient::builder("http://127.0.0.1:54321", "key")
+            .build()
+            .unwrap(),
+    )
+    .layer(ClockLayer::new(clock.clone()))
+    .build();
+    let order = Order {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        quantity: 2,
+        created_at: None,
+        updated_at: None,
+        deleted_at: None,
+    };
+    let mut tx = client.begin();
+    tx.create(&order).unwrap();
+    clock.advance(chrono::Duration::minutes(5));
+    tx.update(&order).unwrap();
+    let (Operation::Create { item: created, .. }, Operation::Update { item: updated, .. }) =
+        (&tx.operations()[0], &tx.operations()[1])
+    else {
+        panic!("unexpected operations: {:?}", tx.operations());
+    };
+    assert_eq!(created["created_at"], serde_json::json!(now));
+    assert_eq!(created["updated_at"], serde_json::json!(now));
+    assert_eq!(updated.get("created_at"), None);
+    assert_eq!(
+        updated["updated_at"],
+        serde_json::json!(now + chrono::Duration::minutes(5))
+    );
+
+    // Timestamps are not part of partial updates.
+    let partial = serde_json::to_value(order.to_partial()).unwrap();
+    assert_eq!(partial.get("created_at"), None);
+    assert_eq!(partial.get("updated_at"), None);
+}
+
+#[test]
+fn test_soft_delete() {
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .build()
+        .unwrap();
+    let id = Uuid::new_v4();
+    assert_eq!(
+        <Order as ExtendedCrud<SupabaseClient>>::SOFT_DELETE_NAME,
+        Some("deleted_at")
+    );
+    let mut tx = client.begin();
+    tx.delete::<Order>(&id).unwrap();
+    let Operation::Update {
+        table,
+        id: key,
+        item,
+        ..
+    } = &tx.operations()[0]
+    else {
+        panic!("unexpected operations: {:?}", tx.operations());
+    };
+    assert_eq!((table.as_str(), key), ("orders", &serde_json::json!(id)));
+    assert!(item["deleted_at"].is_string());
+    assert!(item["updated_at"].is_string());
+}
+
+#[tokio::test]
+async fn test_hooks() {
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .build()
+        .unwrap();
+    let mut user = User {
+        id: Uuid::new_v4(),
+        name: "John Doe".to_string(),
+        email: " John@Example.com ".to_string(),
+        age: 30,
+    };
+    ExtendedCrud::before_update(&mut user, &client)
+        .await
+        .unwrap();
+    assert_eq!(user.email, "john@example.com");
+}
+
+#[test]
+fn test_validation() {
+    let user = User {
+        id: Uuid::new_v4(),
+        name: "".to_string(),
+        email: "john.example.com".to_string(),
+        age: 200,
+    };
+    let errors = user.validate().unwrap_err();
+    let fields = errors
+        .errors
+        .iter()
+        .map(|e| (e.field.as_str(), e.code.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [("name", "length"), ("email", "email"), ("age", "range")]
+    );
+
+    let client = SupabaseClient::builder("http://127.0.0.1:54321", "key")
+        .build()
+        .unwrap();
+    let mut tx = client.begin();
+    let error = tx.create(&user).unwrap_err();
+    assert_eq!(error.downcast_ref::<ValidationErrors>(), Some(&errors));
+    assert!(tx.operations().is_empty());
+
+    let product = Product {
+        product_code: "PROD-001".to_string(),
+        name: "Super Widget".to_string(),
+        price: 19.99,
+        version: 0,
+    };
+    let mut partial_product = ProductUpdate::new();
+    partial_product.name = Some("Super\tWidget".to_string());
+    partial_product.price = Some(-1.0);
+    let errors = partial_product.try_apply_to(&product).unwrap_err();
+    assert_eq!(
+        errors.to_string(),
+        "Validation failed: name must not contain control characters, price must be at least 0"
+    );
+    partial_product.name = None;
+    partial_product.price = Some(24.99);
+    assert_eq!(partial_product.try_apply_to(&product).unwrap().price, 24.99);
+}
+
+#[test]
+fn test_row_formats() {
+    // 列名と値の組で行を返すバックエンドを想定した行の型
+    struct PairsRow(Vec<(&'static str, serde_json::Value)>);
+
+    impl EntityRow for PairsRow {
+        type Error = serde_json::Error;
+
+        fn decode<T: serde::de::DeserializeOwned>(self) -> Result<T, Self::Error> {
+            serde_json::from_value(serde_json::Value::Object(
+                self.0
+                    .into_iter()
+                    .map(|(column, value)| (column.to_string(), value))
+                    .collect(),
+            ))
+        }
+
+        fn from_json(value: serde_json::Value) -> Result<Self, Self::Error> {
+            Err(serde::de::Error::custom(format!(
+                "unsupported row: {}",
+                value
+            )))
+        }
+    }
+
+    let row = PairsRow(vec![
+        ("product_id", "PROD-001".into()),
+        ("name", "Super Widget".into()),
+        ("price", 19.99.into()),
+        ("version", 1.into()),
+    ]);
+    let product = Product::try_from_err(row).unwrap();
+    assert_eq!(product.product_code, "PROD-001");
+
+    let row = JsonRow::from_json(serde_json::to_value(&product).unwrap()).unwrap();
+    assert_eq!(Product::try_from_err(row).unwrap().version, 1);
+}
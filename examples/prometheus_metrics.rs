@@ -0,0 +1,50 @@
+use anyhow::Result;
+use ext_crud_rs::clients::metrics::describe_metrics;
+use ext_crud_rs::prelude::*;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud, PartialEntity)]
+#[table_name("users")]
+struct User {
+    id: Uuid,
+    name: String,
+    email: String,
+    age: u32,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // メトリクスを Prometheus 形式で集計するレコーダーを登録
+    let handle = PrometheusBuilder::new().install_recorder()?;
+    describe_metrics();
+
+    let supabase_api_url = "http://127.0.0.1:54321";
+    let supabase_service_role_key = std::env::var("SUPABASE_SERVICE_ROLE_KEY").map_err(|_| {
+        anyhow::anyhow!("Please set the SUPABASE_SERVICE_ROLE_KEY environment variable")
+    })?;
+    let client = ClientBuilder::new(
+        SupabaseClient::builder(supabase_api_url, &supabase_service_role_key).build()?,
+    )
+    .layer(RetryLayer::default())
+    .layer(MetricsLayer)
+    .build();
+
+    let mut user = User {
+        id: Uuid::new_v4(),
+        name: "John Doe".to_string(),
+        email: "john@example.com".to_string(),
+        age: 30,
+    };
+    user.clone().create(&client).await?;
+    User::read(&client, user.id).await?;
+    user.age = 31;
+    user.update(&client).await?;
+    user.delete(&client).await?;
+
+    // /metrics エンドポイントが返す内容と同じテキスト
+    println!("{}", handle.render());
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use ext_crud_rs::prelude::*;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud, PartialEntity)]
+#[table_name("users")]
+struct User {
+    id: Uuid,
+    name: String,
+    email: String,
+    age: u32,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let supabase_api_url = "http://127.0.0.1:54321";
+    let supabase_service_role_key = std::env::var("SUPABASE_SERVICE_ROLE_KEY").map_err(|_| {
+        anyhow::anyhow!("Please set the SUPABASE_SERVICE_ROLE_KEY environment variable")
+    })?;
+
+    // users テーブルの変更をポーリングせずに受け取る
+    let mut changes = RealtimeClient::new(supabase_api_url, &supabase_service_role_key)
+        .subscribe::<SupabaseClient, User>()
+        .await?;
+
+    let client = SupabaseClient::builder(supabase_api_url, &supabase_service_role_key).build()?;
+    let mut user = User {
+        id: Uuid::new_v4(),
+        name: "John Doe".to_string(),
+        email: "john@example.com".to_string(),
+        age: 30,
+    };
+    user.clone().create(&client).await?;
+    user.age = 31;
+    user.update(&client).await?;
+    user.delete(&client).await?;
+
+    for _ in 0..3 {
+        match changes.next().await.transpose()? {
+            Some(Change::Insert(user)) => println!("Inserted: {:?}", user),
+            Some(Change::Update { new, .. }) => println!("Updated: {:?}", new),
+            Some(Change::Delete { old }) => println!("Deleted: {}", old),
+            None => break,
+        }
+    }
+
+    Ok(())
+}
@@ -1,12 +1,29 @@
 use proc_macro::TokenStream;
 use quote::format_ident;
 use quote::quote;
+use syn::ext::IdentExt;
 use syn::{
-    parse_macro_input, punctuated::Punctuated, token::Comma, Data, DeriveInput, Field, Fields,
-    Ident, Type,
+    parse::ParseStream, parse_macro_input, punctuated::Punctuated, token::Comma, Attribute, Data,
+    DeriveInput, Field, Fields, Ident, LitStr, Path, Token, Type,
 };
 
-#[proc_macro_derive(ExtendedCrud, attributes(table_name, primary_key))]
+#[proc_macro_derive(
+    ExtendedCrud,
+    attributes(
+        table_name,
+        primary_key,
+        belongs_to,
+        has_many,
+        sensitive,
+        version,
+        created_at,
+        updated_at,
+        soft_delete,
+        hooks,
+        validate,
+        custom_decode
+    )
+)]
 pub fn derive_extended_crud(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
@@ -22,8 +39,117 @@ pub fn derive_extended_crud(input: TokenStream) -> TokenStream {
 
     let (primary_key_field, primary_key_type, primary_key_name) = extract_primary_keys(fields);
 
+    let relations = match relation_methods(name, &input.attrs, fields) {
+        Ok(relations) => relations,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let sensitive_fields = fields
+        .iter()
+        .filter(|f| has_attribute(f, "sensitive"))
+        .map(column_name);
+
+    let version = fields
+        .iter()
+        .find(|f| has_attribute(f, "version"))
+        .map(|f| {
+            let field = &f.ident;
+            let column = column_name(f);
+            quote! {
+                const VERSION_NAME: Option<&'static str> = Some(#column);
+
+                fn version(&self) -> Option<i64> {
+                    Some(self.#field as i64)
+                }
+
+                fn set_version(&mut self, version: i64) {
+                    self.#field = version as _;
+                }
+            }
+        });
+
+    let timestamps = [
+        ("created_at", quote!(CREATED_AT_NAME)),
+        ("updated_at", quote!(UPDATED_AT_NAME)),
+        ("soft_delete", quote!(SOFT_DELETE_NAME)),
+    ]
+    .into_iter()
+    .filter_map(|(attribute, constant)| {
+        let column = column_name(fields.iter().find(|f| has_attribute(f, attribute))?);
+        Some(quote! {
+            const #constant: Option<&'static str> = Some(#column);
+        })
+    });
+
+    let soft_delete = fields
+        .iter()
+        .find(|f| has_attribute(f, "soft_delete"))
+        .map(|f| {
+            let field = &f.ident;
+            quote! {
+                fn is_deleted(&self) -> bool {
+                    ::std::option::Option::is_some(&self.#field)
+                }
+            }
+        });
+
+    // With `#[hooks]`, the hook methods forward to the `CrudHooks` impl of the entity.
+    let (hooks_attribute, hooks_bound, hooks) =
+        if input.attrs.iter().any(|attr| attr.path().is_ident("hooks")) {
+            let hooks = [
+            ("before_create", quote!(&mut self)),
+            ("after_create", quote!(&self)),
+            ("before_update", quote!(&mut self)),
+            ("after_update", quote!(&self)),
+            ("before_delete", quote!(&self)),
+            ("after_delete", quote!(&self)),
+            ("after_load", quote!(&mut self)),
+        ]
+        .into_iter()
+        .map(|(hook, receiver)| {
+            let hook = format_ident!("{}", hook);
+            quote! {
+                async fn #hook(#receiver, client: &C) -> ::std::result::Result<(), anyhow::Error> {
+                    <Self as CrudHooks<C>>::#hook(self, client).await
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+            (quote!(#[async_trait]), quote!(Self: CrudHooks<C>,), hooks)
+        } else {
+            (quote!(), quote!(), vec![])
+        };
+
+    let decode = row_decoding(name, &input.attrs);
+
+    let validations = match fields
+        .iter()
+        .map(field_validations)
+        .collect::<syn::Result<Vec<_>>>()
+    {
+        Ok(validations) => validations.into_iter().flatten(),
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let columns = match column_constants(name, fields) {
+        Ok(columns) => columns,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     let expanded = quote! {
+        #relations
+
+        impl Validate for #name {
+            fn validate(&self) -> ::std::result::Result<(), ValidationErrors> {
+                let mut errors = ValidationErrors::new();
+                #(#validations)*
+                errors.into_result()
+            }
+        }
+
         impl #name {
+            #columns
+
             fn primary_key_name() -> &'static str {
                 #primary_key_name
             }
@@ -31,29 +157,43 @@ pub fn derive_extended_crud(input: TokenStream) -> TokenStream {
                 &self.#primary_key_field
             }
         }
-        impl<C: Client> ExtendedCrud<C> for #name {
+        #hooks_attribute
+        impl<C: Client> ExtendedCrud<C> for #name
+        where
+            Self: TryFromError<C::Row, <C::Row as EntityRow>::Error>,
+            #hooks_bound
+        {
             type PrimaryKey = #primary_key_type;
 
             const TABLE_NAME: &'static str = #table_name;
 
             const PRIMARY_KEY_NAME: &'static str = #primary_key_name;
 
+            const SENSITIVE_FIELDS: &'static [&'static str] = &[#(#sensitive_fields),*];
+
             fn primary_key(&self) -> &Self::PrimaryKey {
                  &self.#primary_key_field
              }
-        }
-        impl TryFromError<serde_json::Value, serde_json::Error> for #name {
 
-            fn try_from_err(value: serde_json::Value) -> Result<Self, serde_json::Error> {
-                serde_json::from_value(value).map_err(Into::into)
-            }
+            #version
+
+            #(#timestamps)*
+
+            #soft_delete
+
+            #(#hooks)*
         }
+
+        #decode
     };
 
     TokenStream::from(expanded)
 }
 
-#[proc_macro_derive(PartialEntity, attributes(partial_entity_name, primary_key))]
+#[proc_macro_derive(
+    PartialEntity,
+    attributes(partial_entity_name, primary_key, created_at, updated_at)
+)]
 pub fn partial_entity(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let original_name = &input.ident;
@@ -69,6 +209,12 @@ pub fn partial_entity(input: TokenStream) -> TokenStream {
 
     let (primary_key_field, primary_key_type, primary_key_name) = extract_primary_keys(fields);
 
+    // Timestamps are managed by `ExtendedCrud`, so partial updates never carry them.
+    let fields = fields
+        .iter()
+        .filter(|f| !has_attribute(f, "created_at") && !has_attribute(f, "updated_at"))
+        .collect::<Vec<_>>();
+
     let partial_fields = fields.iter().map(|f| {
         let name = &f.ident;
         let ty = &f.ty;
@@ -100,6 +246,28 @@ pub fn partial_entity(input: TokenStream) -> TokenStream {
         }
     });
 
+    let diff_fields = fields.iter().map(|f| {
+        let name = &f.ident;
+        quote! {
+            #name: (serde_json::to_value(&before.#name).ok()
+                != serde_json::to_value(&after.#name).ok())
+            .then(|| after.#name.clone())
+        }
+    });
+
+    let change_fields = fields.iter().map(|f| {
+        let name = &f.ident;
+        let column = column_name(f);
+        quote! {
+            if let Some(ref value) = self.#name {
+                changes.insert(
+                    #column.to_string(),
+                    serde_json::to_value(value).unwrap_or_default(),
+                );
+            }
+        }
+    });
+
     let expanded = quote! {
         #[derive(Debug, Clone, Serialize, Deserialize)]
         pub struct #partial_name {
@@ -134,12 +302,402 @@ pub fn partial_entity(input: TokenStream) -> TokenStream {
             fn primary_key(&self) -> Option<Self::PrimaryKey> {
                 self.#primary_key_field.clone()
             }
+
+            fn diff(before: &#original_name, after: &#original_name) -> Self {
+                Self {
+                    #(#diff_fields,)*
+                }
+            }
+
+            fn changes(&self) -> serde_json::Map<String, serde_json::Value> {
+                let mut changes = serde_json::Map::new();
+                #(#change_fields)*
+                changes
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+#[proc_macro_derive(ExtendedView, attributes(view_of, custom_decode))]
+pub fn derive_extended_view(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let entity = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("view_of"))
+        .map(|attr| attr.parse_args::<Path>().unwrap())
+        .expect("view_of attribute is required");
+
+    let fields = fields_named_from_input(&input);
+
+    let columns = fields.iter().map(column_name);
+
+    let decode = row_decoding(name, &input.attrs);
+
+    let expanded = quote! {
+        impl<C: Client> ExtendedView<C> for #name
+        where
+            #entity: ExtendedCrud<C>,
+        {
+            type Entity = #entity;
+
+            const COLUMNS: &'static [&'static str] = &[#(#columns),*];
         }
+
+        #decode
     };
 
     TokenStream::from(expanded)
 }
 
+/// Associated constants of `ExtendedCrud`, which an inherent column constant of the same
+/// name would shadow.
+const RESERVED_CONSTANTS: &[&str] = &[
+    "TABLE_NAME",
+    "PRIMARY_KEY_NAME",
+    "SENSITIVE_FIELDS",
+    "VERSION_NAME",
+    "CREATED_AT_NAME",
+    "UPDATED_AT_NAME",
+    "SOFT_DELETE_NAME",
+];
+
+/// A `Column` constant per field, named after the field in upper case.
+fn column_constants(
+    name: &Ident,
+    fields: &Punctuated<Field, Comma>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut constants = Vec::<String>::new();
+    let mut columns = vec![];
+    for f in fields {
+        let ident = f.ident.as_ref().unwrap();
+        let constant = ident.unraw().to_string().to_uppercase();
+        if RESERVED_CONSTANTS.contains(&constant.as_str()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "field `{}` would generate the column constant `{}`, which shadows `ExtendedCrud::{}`",
+                    ident, constant, constant
+                ),
+            ));
+        }
+        if constants.contains(&constant) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "field `{}` would generate the column constant `{}` of another field",
+                    ident, constant
+                ),
+            ));
+        }
+        let ty = &f.ty;
+        let column = column_name(f);
+        let constant_ident = format_ident!("{}", constant);
+        columns.push(quote! {
+            pub const #constant_ident: Column<#name, #ty> = Column::new(#column);
+        });
+        constants.push(constant);
+    }
+    Ok(quote!(#(#columns)*))
+}
+
+/// The `TryFromError` impl deserializing `name` from the rows of any client with
+/// `EntityRow::decode`, left out with `#[custom_decode]` for entities decoding themselves,
+/// e.g. from the native row type of their client.
+fn row_decoding(name: &Ident, attrs: &[Attribute]) -> proc_macro2::TokenStream {
+    if attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("custom_decode"))
+    {
+        return quote!();
+    }
+    quote! {
+        impl<R: EntityRow> TryFromError<R, <R as EntityRow>::Error> for #name {
+            fn try_from_err(row: R) -> Result<Self, <R as EntityRow>::Error> {
+                row.decode()
+            }
+        }
+    }
+}
+
+/// The checks of the `#[validate(...)]` attributes of `field`, skipped when an `Option`
+/// field is `None`.
+fn field_validations(field: &Field) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let ident = &field.ident;
+    let column = column_name(field);
+    let mut checks = vec![];
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("validate"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("email") {
+                checks.push(quote!(errors.check_email(#column, value);));
+            } else if meta.path.is_ident("length") {
+                let (min, max) = parse_bounds(&meta)?;
+                let min = optional(min.map(|min| quote!((#min) as usize)));
+                let max = optional(max.map(|max| quote!((#max) as usize)));
+                checks.push(quote!(errors.check_length(#column, value, #min, #max);));
+            } else if meta.path.is_ident("range") {
+                let (min, max) = parse_bounds(&meta)?;
+                let min = optional(min.map(|min| quote!((#min) as f64)));
+                let max = optional(max.map(|max| quote!((#max) as f64)));
+                checks.push(quote!(errors.check_range(#column, *value as f64, #min, #max);));
+            } else if meta.path.is_ident("custom") {
+                let function = meta.value()?.parse::<LitStr>()?.parse::<Path>()?;
+                checks.push(quote!(errors.check_custom(#column, #function(value));));
+            } else {
+                return Err(meta.error("expected length, email, range or custom"));
+            }
+            Ok(())
+        })?;
+    }
+    if checks.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(if is_option(&field.ty) {
+        quote! {
+            if let Some(value) = &self.#ident {
+                #(#checks)*
+            }
+        }
+    } else {
+        quote! {
+            {
+                let value = &self.#ident;
+                #(#checks)*
+            }
+        }
+    }))
+}
+
+fn parse_bounds(
+    meta: &syn::meta::ParseNestedMeta,
+) -> syn::Result<(Option<syn::Expr>, Option<syn::Expr>)> {
+    let (mut min, mut max) = (None, None);
+    meta.parse_nested_meta(|bound| {
+        if bound.path.is_ident("min") {
+            min = Some(bound.value()?.parse()?);
+        } else if bound.path.is_ident("max") {
+            max = Some(bound.value()?.parse()?);
+        } else {
+            return Err(bound.error("expected min or max"));
+        }
+        Ok(())
+    })?;
+    Ok((min, max))
+}
+
+fn optional(value: Option<proc_macro2::TokenStream>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None),
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn has_attribute(field: &Field, name: &str) -> bool {
+    field.attrs.iter().any(|attr| attr.path().is_ident(name))
+}
+
+struct Relation {
+    target: Path,
+    column: String,
+    name: Ident,
+}
+
+fn parse_relation(attr: &Attribute, column_arg: &str) -> syn::Result<Relation> {
+    let (target, column, name) = attr.parse_args_with(|input: ParseStream| {
+        let target: Path = input.parse()?;
+        let mut column = None;
+        let mut name = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            let arg: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            if arg == column_arg {
+                column = Some(value.value());
+            } else if arg == "name" {
+                name = Some(value.parse::<Ident>()?);
+            } else {
+                return Err(syn::Error::new(arg.span(), "unknown relation argument"));
+            }
+        }
+        Ok((target, column, name))
+    })?;
+    let column = column.ok_or_else(|| {
+        syn::Error::new_spanned(attr, format!("`{}` argument is required", column_arg))
+    })?;
+    let target_name = &target.segments.last().unwrap().ident;
+    let name = name.unwrap_or_else(|| {
+        let name = to_snake_case(&target_name.to_string());
+        if attr.path().is_ident("has_many") {
+            format_ident!("{}", pluralize(&name))
+        } else {
+            format_ident!("{}", name)
+        }
+    });
+    Ok(Relation {
+        target,
+        column,
+        name,
+    })
+}
+
+/// The English plural of the snake case `name`, for the accessors of `has_many`; irregular
+/// plurals need the `name` argument.
+fn pluralize(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix('y') {
+        if !stem.ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{}ies", stem);
+        }
+    }
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+    {
+        return format!("{}es", name);
+    }
+    format!("{}s", name)
+}
+
+fn relation_methods(
+    struct_name: &Ident,
+    attrs: &[Attribute],
+    fields: &Punctuated<Field, Comma>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut methods = vec![];
+    for attr in attrs {
+        if attr.path().is_ident("belongs_to") {
+            let Relation {
+                target,
+                column,
+                name,
+            } = parse_relation(attr, "key")?;
+            let eager_name = format_ident!("with_{}", name);
+            let key = fields
+                .iter()
+                .find(|f| f.ident.as_ref().is_some_and(|ident| *ident == column))
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        attr,
+                        format!("belongs_to key `{}` must be a field", column),
+                    )
+                })?;
+            let (key_field, key_type) = (&key.ident, &key.ty);
+            methods.push(quote! {
+                pub async fn #name<C: Client>(&self, client: &C) -> anyhow::Result<#target>
+                where
+                    #target: ExtendedCrud<C, PrimaryKey = #key_type>,
+                {
+                    <#target as ExtendedCrud<C>>::read(client, self.#key_field.clone()).await
+                }
+
+                pub async fn #eager_name<C: Client>(
+                    items: Vec<Self>,
+                    client: &C,
+                ) -> anyhow::Result<Vec<(Self, Option<#target>)>>
+                where
+                    #target: ExtendedCrud<C, PrimaryKey = #key_type>,
+                {
+                    let loaded =
+                        load_belongs_to(client, &items, |item: &Self| item.#key_field.clone())
+                            .await?;
+                    Ok(items.into_iter().zip(loaded).collect())
+                }
+            });
+        } else if attr.path().is_ident("has_many") {
+            let Relation {
+                target,
+                column,
+                name,
+            } = parse_relation(attr, "foreign_key")?;
+            let eager_name = format_ident!("with_{}", name);
+            methods.push(quote! {
+                pub async fn #name<C: Client>(&self, client: &C) -> anyhow::Result<Vec<#target>>
+                where
+                    Self: ExtendedCrud<C>,
+                    #target: ExtendedCrud<C>,
+                {
+                    let mut loaded =
+                        load_has_many(client, std::slice::from_ref(self), #column).await?;
+                    Ok(loaded.pop().unwrap_or_default())
+                }
+
+                pub async fn #eager_name<C: Client>(
+                    items: Vec<Self>,
+                    client: &C,
+                ) -> anyhow::Result<Vec<(Self, Vec<#target>)>>
+                where
+                    Self: ExtendedCrud<C>,
+                    #target: ExtendedCrud<C>,
+                {
+                    let loaded = load_has_many(client, &items, #column).await?;
+                    Ok(items.into_iter().zip(loaded).collect())
+                }
+            });
+        }
+    }
+    if methods.is_empty() {
+        return Ok(quote! {});
+    }
+    Ok(quote! {
+        impl #struct_name {
+            #(#methods)*
+        }
+    })
+}
+
+fn column_name(field: &Field) -> String {
+    let mut column = field.ident.as_ref().unwrap().to_string();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                column = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+    }
+    column
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
 fn fields_named_from_input(input: &DeriveInput) -> &Punctuated<Field, Comma> {
     match &input.data {
         Data::Struct(data) => fields_named(&data.fields),
@@ -0,0 +1,828 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::error::AuditFailed;
+use crate::clients::ids::{id_string, plain_id, plain_ids};
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+use crate::entity::extend::{ExtendedCrud, PartialEntity};
+use crate::telemetry::redact;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// One change of an audited row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub table: String,
+    pub key: String,
+    pub id: serde_json::Value,
+    pub action: AuditAction,
+    pub actor: Option<String>,
+    pub at: DateTime<Utc>,
+    /// The row before the change, `None` for creates.
+    pub before: Option<serde_json::Value>,
+    /// The row after the change, `None` for deletes removing the row.
+    pub after: Option<serde_json::Value>,
+    /// The new values of the changed columns only, by `PartialEntity::diff` for tables
+    /// audited with `AuditConfig::partial_entity`, else by comparing the untyped rows.
+    pub changes: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Destination of audit entries.
+#[async_trait]
+pub trait AuditSink: Send + Sync + 'static {
+    async fn record(&self, entry: &AuditEntry) -> Result<()>;
+}
+
+/// Inserts entries as rows of `table` through `client`.
+pub struct TableSink<C: Client> {
+    client: C,
+    table: String,
+}
+
+impl<C: Client> TableSink<C> {
+    pub fn new(client: C, table: &str) -> Self {
+        Self {
+            client,
+            table: table.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> AuditSink for TableSink<C> {
+    async fn record(&self, entry: &AuditEntry) -> Result<()> {
+        self.client.create(&self.table, entry).await
+    }
+}
+
+/// Appends entries to a file as JSON lines.
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileSink {
+    async fn record(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        Ok(())
+    }
+}
+
+/// Sends entries to a channel, e.g. to ship them from a background task.
+pub struct ChannelSink {
+    sender: mpsc::UnboundedSender<AuditEntry>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: mpsc::UnboundedSender<AuditEntry>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl AuditSink for ChannelSink {
+    async fn record(&self, entry: &AuditEntry) -> Result<()> {
+        self.sender
+            .send(entry.clone())
+            .map_err(|_| anyhow!("ChannelSink.record, channel closed"))
+    }
+}
+
+/// The changed columns between two rows of a table.
+type Diff = fn(
+    &serde_json::Value,
+    &serde_json::Value,
+) -> Result<serde_json::Map<String, serde_json::Value>>;
+
+#[derive(Debug, Clone)]
+struct AuditedTable {
+    key: String,
+    sensitive: &'static [&'static str],
+    soft_delete: Option<&'static str>,
+    diff: Diff,
+}
+
+/// Which tables are audited, and as whom.
+#[derive(Debug, Clone, Default)]
+pub struct AuditConfig {
+    tables: HashMap<String, AuditedTable>,
+    actor: Option<String>,
+}
+
+impl AuditConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Audits `table`, whose rows are identified by `key`.
+    pub fn table(mut self, table: &str, key: &str) -> Self {
+        self.tables.insert(
+            table.to_string(),
+            AuditedTable {
+                key: key.to_string(),
+                sensitive: &[],
+                soft_delete: None,
+                diff: row_diff,
+            },
+        );
+        self
+    }
+
+    /// Audits the table of `E`, redacting its sensitive columns and recording the updates
+    /// setting its soft delete column as deletes.
+    pub fn entity<C: Client, E: ExtendedCrud<C>>(mut self) -> Self {
+        self.tables.insert(
+            E::TABLE_NAME.to_string(),
+            AuditedTable {
+                key: E::PRIMARY_KEY_NAME.to_string(),
+                sensitive: E::SENSITIVE_FIELDS,
+                soft_delete: E::SOFT_DELETE_NAME,
+                diff: row_diff,
+            },
+        );
+        self
+    }
+
+    /// Like `entity`, but computes the changes of updates with `PartialEntity::diff` of
+    /// `P` on the rows decoded as `E`.
+    pub fn partial_entity<C, E, P>(self) -> Self
+    where
+        C: Client,
+        E: ExtendedCrud<C>,
+        P: PartialEntity<E> + DeserializeOwned,
+    {
+        let mut config = self.entity::<C, E>();
+        if let Some(audited) = config.tables.get_mut(E::TABLE_NAME) {
+            audited.diff = partial_diff::<E, P>;
+        }
+        config
+    }
+
+    pub fn actor(mut self, actor: &str) -> Self {
+        self.actor = Some(actor.to_string());
+        self
+    }
+}
+
+/// Records every create, update and delete of the audited tables into a sink, with the
+/// row before and after the change.
+///
+/// Rows are read through the key the write names before updates and deletes to capture
+/// their previous state, and read back by the audited key after creates and updates, so
+/// that columns set by the database show up. `update_where` on an audited table must
+/// filter on its audited key, and fails otherwise. Updates setting the soft delete column
+/// of a table audited with `AuditConfig::entity` are recorded as deletes.
+///
+/// Entries are recorded once the write succeeded. When reading back or the sink fails,
+/// the write is already applied: the call fails with an `AuditFailed` in its error chain,
+/// which `RetryingClient` does not retry.
+pub struct AuditingClient<C: Client> {
+    inner: C,
+    sink: Arc<dyn AuditSink>,
+    config: AuditConfig,
+}
+
+impl<C: Client> AuditingClient<C> {
+    pub fn new<S: AuditSink>(inner: C, sink: S, config: AuditConfig) -> Self {
+        Self {
+            inner,
+            sink: Arc::new(sink),
+            config,
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a client recording `actor` as the author of its changes, sharing the sink.
+    pub fn with_actor(&self, actor: &str) -> Self
+    where
+        C: Clone,
+    {
+        Self {
+            inner: self.inner.clone(),
+            sink: self.sink.clone(),
+            config: self.config.clone().actor(actor),
+        }
+    }
+
+    /// The current rows of `table` whose `column` is one of `ids`, by `id_string` of their
+    /// audited key, when `table` is audited.
+    async fn snapshot(
+        &self,
+        table: &str,
+        column: &str,
+        ids: Vec<serde_json::Value>,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let Some(audited) = self.config.tables.get(table) else {
+            return Ok(HashMap::new());
+        };
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let rows = self
+            .inner
+            .find_by_keys(table, column, ids)
+            .await
+            .context("AuditingClient.snapshot failed")?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((id_string(row.get(&audited.key)?), row)))
+            .collect())
+    }
+
+    /// The stored rows of `befores` once written, so that columns set by the database
+    /// show up in the entries.
+    async fn read_back(
+        &self,
+        table: &str,
+        befores: &HashMap<String, serde_json::Value>,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let Some(key) = self.key(table) else {
+            return Ok(HashMap::new());
+        };
+        let ids = befores
+            .values()
+            .filter_map(|row| row.get(key).cloned())
+            .collect();
+        self.snapshot(table, key, ids).await
+    }
+
+    /// Records the creation of `item` with its stored row, or with `item` itself when it
+    /// does not hold its key.
+    async fn record_create(&self, table: &str, item: serde_json::Value) -> Result<()> {
+        let Some(id) = self.key(table).and_then(|key| item.get(key)).map(id_string) else {
+            return self
+                .record(table, AuditAction::Create, None, Some(item))
+                .await;
+        };
+        let mut rows = self
+            .read_back(table, &HashMap::from([(id.clone(), item.clone())]))
+            .await?;
+        let after = rows.remove(&id).unwrap_or(item);
+        self.record(table, AuditAction::Create, None, Some(after))
+            .await
+    }
+
+    /// Records an update of every row of `befores`, then read back as `afters`.
+    async fn record_updates(
+        &self,
+        table: &str,
+        befores: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        let mut afters = self.read_back(table, &befores).await?;
+        let soft_delete = self
+            .config
+            .tables
+            .get(table)
+            .and_then(|audited| audited.soft_delete);
+        for (id, before) in befores {
+            let after = afters.remove(&id);
+            let is_set = |row: Option<&serde_json::Value>, column: &str| {
+                row.and_then(|row| row.get(column))
+                    .is_some_and(|value| !value.is_null())
+            };
+            let action = match soft_delete {
+                Some(column)
+                    if !is_set(Some(&before), column) && is_set(after.as_ref(), column) =>
+                {
+                    AuditAction::Delete
+                }
+                _ => AuditAction::Update,
+            };
+            self.record(table, action, Some(before), after).await?;
+        }
+        Ok(())
+    }
+
+    async fn record_deletes(
+        &self,
+        table: &str,
+        befores: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        for before in befores.into_values() {
+            self.record(table, AuditAction::Delete, Some(before), None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn record(
+        &self,
+        table: &str,
+        action: AuditAction,
+        before: Option<serde_json::Value>,
+        after: Option<serde_json::Value>,
+    ) -> Result<()> {
+        let Some(audited) = self.config.tables.get(table) else {
+            return Ok(());
+        };
+        let id = after
+            .as_ref()
+            .or(before.as_ref())
+            .and_then(|row| row.get(&audited.key))
+            .cloned()
+            .unwrap_or_default();
+        let changes = match (&before, &after) {
+            (Some(before), Some(after)) => (audited.diff)(before, after)?,
+            (None, Some(serde_json::Value::Object(after))) => after.clone(),
+            _ => serde_json::Map::new(),
+        };
+        let entry = AuditEntry {
+            table: table.to_string(),
+            key: audited.key.clone(),
+            id,
+            action,
+            actor: self.config.actor.clone(),
+            at: self.inner.clock().now(),
+            before: before.map(|row| redact(&row, audited.sensitive)),
+            after: after.map(|row| redact(&row, audited.sensitive)),
+            changes: match redact(&changes.into(), audited.sensitive) {
+                serde_json::Value::Object(changes) => changes,
+                _ => serde_json::Map::new(),
+            },
+        };
+        self.sink
+            .record(&entry)
+            .await
+            .context("AuditingClient.record failed")
+    }
+
+    fn is_audited(&self, table: &str) -> bool {
+        self.config.tables.contains_key(table)
+    }
+
+    fn key(&self, table: &str) -> Option<&str> {
+        self.config
+            .tables
+            .get(table)
+            .map(|audited| audited.key.as_str())
+    }
+}
+
+/// The columns of `after` whose value differs from `before`.
+fn row_diff(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let Some(after) = after.as_object() else {
+        return Ok(serde_json::Map::new());
+    };
+    Ok(after
+        .iter()
+        .filter(|(column, value)| before.get(column.as_str()) != Some(value))
+        .map(|(column, value)| (column.clone(), value.clone()))
+        .collect())
+}
+
+/// The changes of `PartialEntity::diff` between `before` and `after` decoded as `E`.
+fn partial_diff<E, P>(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+) -> Result<serde_json::Map<String, serde_json::Value>>
+where
+    E: Serialize + DeserializeOwned,
+    P: PartialEntity<E> + DeserializeOwned,
+{
+    let before: E = serde_json::from_value(before.clone())?;
+    let after: E = serde_json::from_value(after.clone())?;
+    Ok(P::diff(&before, &after).changes())
+}
+
+/// Wraps clients into an `AuditingClient` recording into `sink`.
+#[derive(Clone)]
+pub struct AuditLayer {
+    sink: Arc<dyn AuditSink>,
+    config: AuditConfig,
+}
+
+impl AuditLayer {
+    pub fn new<S: AuditSink>(sink: S, config: AuditConfig) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            config,
+        }
+    }
+}
+
+impl<C: Client> Layer<C> for AuditLayer {
+    type Client = AuditingClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        AuditingClient {
+            inner,
+            sink: self.sink.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for AuditingClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        if !self.is_audited(table) {
+            return self.inner.create(table, item).await;
+        }
+        let item = serde_json::to_value(item)?;
+        self.inner.create(table, &item).await?;
+        self.record_create(table, item)
+            .await
+            .with_context(|| audit_failed(table))
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_by_keys(table, key, ids).await
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner
+            .find_by_keys_select(table, key, ids, columns)
+            .await
+    }
+
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_rows(table, key, ids).await
+    }
+
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        self.inner.find_by_keys_typed(table, key, ids).await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_range(table, key, after, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner
+            .find_where_rows(table, filters, order, limit)
+            .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_range_rows(table, key, after, limit).await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        if !self.is_audited(table) {
+            return self.inner.update_by_keys(table, key, items).await;
+        }
+        let ids = items.iter().map(|(id, _)| plain_id(id.as_ref())).collect();
+        let befores = self.snapshot(table, key, ids).await?;
+        self.inner.update_by_keys(table, key, items).await?;
+        self.record_updates(table, befores)
+            .await
+            .with_context(|| audit_failed(table))
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        if !self.is_audited(table) {
+            return self.inner.delete_by_keys(table, key, ids).await;
+        }
+        let ids = plain_ids(&ids)?;
+        let befores = self.snapshot(table, key, ids.clone()).await?;
+        self.inner.delete_by_keys(table, key, ids).await?;
+        self.record_deletes(table, befores)
+            .await
+            .with_context(|| audit_failed(table))
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        let Some(key) = self.key(table) else {
+            return self.inner.update_where(table, filters, item).await;
+        };
+        let Some((_, id)) = filters.iter().find(|(column, _)| *column == key) else {
+            bail!(
+                "AuditingClient.update_where, updates of {} must filter on {}",
+                table,
+                key
+            );
+        };
+        let befores = self.snapshot(table, key, vec![plain_id(id)]).await?;
+        let updated = self.inner.update_where(table, filters, item).await?;
+        if updated > 0 {
+            self.record_updates(table, befores)
+                .await
+                .with_context(|| audit_failed(table))?;
+        }
+        Ok(updated)
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        let mut befores = Vec::with_capacity(operations.len());
+        for operation in &operations {
+            befores.push(match operation {
+                Operation::Update { table, key, id, .. }
+                | Operation::Delete { table, key, id, .. } => {
+                    self.snapshot(table, key, vec![id.clone()]).await?
+                }
+                Operation::Create { .. } => HashMap::new(),
+            });
+        }
+        self.inner.execute_batch(operations.clone()).await?;
+        for (operation, befores) in operations.into_iter().zip(befores) {
+            match operation {
+                Operation::Create { table, item } => self
+                    .record_create(&table, item)
+                    .await
+                    .with_context(|| audit_failed(&table))?,
+                Operation::Update { table, .. } => self
+                    .record_updates(&table, befores)
+                    .await
+                    .with_context(|| audit_failed(&table))?,
+                Operation::Delete { table, .. } => self
+                    .record_deletes(&table, befores)
+                    .await
+                    .with_context(|| audit_failed(&table))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+fn audit_failed(table: &str) -> AuditFailed {
+    AuditFailed {
+        table: table.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use serde_json::json;
+
+    fn auditing_client() -> (
+        AuditingClient<MemoryClient>,
+        mpsc::UnboundedReceiver<AuditEntry>,
+    ) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let config = AuditConfig::new().table("users", "id").actor("admin");
+        let client = AuditingClient::new(MemoryClient::default(), ChannelSink::new(sender), config);
+        (client, receiver)
+    }
+
+    #[tokio::test]
+    async fn records_changes_of_audited_tables() {
+        let (client, mut receiver) = auditing_client();
+        client
+            .create("users", &json!({ "id": 1, "name": "john", "age": 30 }))
+            .await
+            .unwrap();
+        client
+            .update_by_keys("users", "id", vec![("1", json!({ "age": 31 }))])
+            .await
+            .unwrap();
+        client.delete_by_keys("users", "id", vec![1]).await.unwrap();
+        client.create("orders", &json!({ "id": 1 })).await.unwrap();
+
+        let create = receiver.recv().await.unwrap();
+        assert_eq!(
+            (create.action, create.id, create.actor.as_deref()),
+            (AuditAction::Create, json!(1), Some("admin"))
+        );
+        assert_eq!(create.changes.len(), 3);
+
+        let update = receiver.recv().await.unwrap();
+        assert_eq!(update.action, AuditAction::Update);
+        assert_eq!(update.before.unwrap()["age"], 30);
+        assert_eq!(update.after.unwrap()["age"], 31);
+        assert_eq!(json!(update.changes), json!({ "age": 31 }));
+
+        let delete = receiver.recv().await.unwrap();
+        assert_eq!((delete.action, delete.after), (AuditAction::Delete, None));
+        assert_eq!(delete.before.unwrap()["name"], "john");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn audits_writes_through_other_keys() {
+        let (client, mut receiver) = auditing_client();
+        client
+            .create("users", &json!({ "id": 1, "name": "john", "age": 30 }))
+            .await
+            .unwrap();
+        client
+            .update_by_keys("users", "name", vec![("john", json!({ "age": 31 }))])
+            .await
+            .unwrap();
+        client
+            .delete_by_keys("users", "name", vec!["john"])
+            .await
+            .unwrap();
+
+        receiver.recv().await.unwrap();
+        let update = receiver.recv().await.unwrap();
+        assert_eq!((update.action, update.id), (AuditAction::Update, json!(1)));
+        assert_eq!(
+            update.after.unwrap(),
+            json!({ "id": 1, "name": "john", "age": 31 })
+        );
+        let delete = receiver.recv().await.unwrap();
+        assert_eq!((delete.action, delete.id), (AuditAction::Delete, json!(1)));
+
+        let error = client
+            .update_where(
+                "users",
+                &[("name", "john".to_string())],
+                &json!({ "age": 32 }),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("must filter on id"));
+    }
+
+    mod entity {
+        use crate::clients::client::Client;
+        use crate::entity::column::Column;
+        use crate::entity::extend::{ExtendedCrud, PartialEntity, TryFromError};
+        use crate::entity::row::EntityRow;
+        use crate::entity::validate::{Validate, ValidationErrors};
+        use ext_crud_derive::{ExtendedCrud, PartialEntity};
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud, PartialEntity)]
+        #[table_name("users")]
+        pub struct User {
+            pub id: i64,
+            #[serde(rename = "full_name")]
+            pub name: String,
+            #[soft_delete]
+            pub deleted_at: Option<String>,
+        }
+    }
+
+    #[tokio::test]
+    async fn records_entity_changes_and_soft_deletes() {
+        use entity::{PartialUser, User};
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let config = AuditConfig::new().partial_entity::<MemoryClient, User, PartialUser>();
+        let client = AuditingClient::new(MemoryClient::default(), ChannelSink::new(sender), config);
+        let user = User {
+            id: 1,
+            name: "john".to_string(),
+            deleted_at: None,
+        };
+        user.create(&client).await.unwrap();
+        let mut user = User::read(&client, 1).await.unwrap();
+        user.name = "jane".to_string();
+        user.update(&client).await.unwrap();
+        user.delete(&client).await.unwrap();
+
+        receiver.recv().await.unwrap();
+        let update = receiver.recv().await.unwrap();
+        assert_eq!(update.action, AuditAction::Update);
+        assert_eq!(json!(update.changes), json!({ "full_name": "jane" }));
+        let delete = receiver.recv().await.unwrap();
+        assert_eq!(delete.action, AuditAction::Delete);
+        assert_eq!(delete.changes.keys().collect::<Vec<_>>(), ["deleted_at"]);
+        assert!(delete.after.unwrap()["deleted_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn marks_sink_failures_after_writes() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        drop(receiver);
+        let client = AuditingClient::new(
+            MemoryClient::default(),
+            ChannelSink::new(sender),
+            AuditConfig::new().table("users", "id"),
+        );
+        let error = client
+            .create("users", &json!({ "id": 1 }))
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<AuditFailed>().is_some());
+        assert!(!crate::clients::retry::is_transient(&error));
+        assert_eq!(
+            client.inner().calls(),
+            ["create users", "find_by_keys users id 1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn appends_entries_to_files() {
+        let path =
+            std::env::temp_dir().join(format!("ext-crud-audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let client = AuditingClient::new(
+            MemoryClient::default(),
+            FileSink::new(&path),
+            AuditConfig::new().table("users", "id"),
+        );
+        client.create("users", &json!({ "id": 1 })).await.unwrap();
+        client.create("users", &json!({ "id": 2 })).await.unwrap();
+
+        let text = tokio::fs::read_to_string(&path).await.unwrap();
+        let _ = tokio::fs::remove_file(&path).await;
+        let entries = text
+            .lines()
+            .map(|line| serde_json::from_str::<AuditEntry>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].id, json!(2));
+    }
+}
@@ -0,0 +1,476 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub ttl: Duration,
+    pub max_capacity: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60),
+            max_capacity: 10_000,
+        }
+    }
+}
+
+/// `(table, key, id)`, with the id in its JSON form.
+type CacheKey = (String, String, String);
+
+struct CacheEntry {
+    rows: Vec<serde_json::Value>,
+    inserted_at: Instant,
+    /// Position of the entry in `Entries::order`.
+    sequence: u64,
+}
+
+/// The cached rows, with their keys in insertion order. Entries share one TTL, so the
+/// oldest entry is also the first to expire, and is evicted without scanning the map.
+#[derive(Default)]
+struct Entries {
+    rows: HashMap<CacheKey, CacheEntry>,
+    order: BTreeMap<u64, CacheKey>,
+    next_sequence: u64,
+}
+
+impl Entries {
+    fn insert(&mut self, key: CacheKey, rows: Vec<serde_json::Value>) {
+        self.remove(&key);
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.order.insert(sequence, key.clone());
+        self.rows.insert(
+            key,
+            CacheEntry {
+                rows,
+                inserted_at: Instant::now(),
+                sequence,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.rows.remove(key) {
+            self.order.remove(&entry.sequence);
+        }
+    }
+
+    fn remove_oldest(&mut self) {
+        if let Some((_, key)) = self.order.pop_first() {
+            self.rows.remove(&key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+        self.order.clear();
+    }
+}
+
+/// Read-through cache of `find_by_keys` results per `(table, key, id)`.
+///
+/// Any write to a table drops all of its entries, so only writes made through this client
+/// are seen before the TTL expires. Reads still in flight during a write are not cached.
+/// Ids without rows and `find_by_keys_select` projections are not cached.
+pub struct CachedClient<C: Client> {
+    inner: C,
+    config: CacheConfig,
+    /// Bumped on every invalidation of a table, locked before `entries`.
+    generations: Mutex<HashMap<String, u64>>,
+    entries: Mutex<Entries>,
+}
+
+impl<C: Client> CachedClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self::with_config(inner, CacheConfig::default())
+    }
+
+    pub fn with_config(inner: C, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            generations: Mutex::new(HashMap::new()),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn invalidate_table(&self, table: &str) {
+        debug!("CachedClient.invalidate_table: {}", table);
+        let mut generations = self.generations.lock().unwrap();
+        *generations.entry(table.to_string()).or_default() += 1;
+        let mut entries = self.entries.lock().unwrap();
+        entries
+            .rows
+            .retain(|(cached_table, _, _), _| cached_table != table);
+        entries
+            .order
+            .retain(|_, (cached_table, _, _)| cached_table != table);
+    }
+
+    pub fn clear(&self) {
+        let mut generations = self.generations.lock().unwrap();
+        generations
+            .values_mut()
+            .for_each(|generation| *generation += 1);
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<Vec<serde_json::Value>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.rows.get(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.config.ttl => {
+                Some(entry.rows.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// The generation of `table`, to be read before fetching rows to `insert`.
+    fn generation(&self, table: &str) -> u64 {
+        *self
+            .generations
+            .lock()
+            .unwrap()
+            .entry(table.to_string())
+            .or_default()
+    }
+
+    /// Caches `rows` unless the table was invalidated since `generation` was read, as they
+    /// may have been fetched before the write.
+    fn insert(&self, key: CacheKey, rows: Vec<serde_json::Value>, generation: u64) {
+        let generations = self.generations.lock().unwrap();
+        if generations.get(&key.0) != Some(&generation) {
+            return;
+        }
+        if self.config.max_capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key);
+        while entries.rows.len() >= self.config.max_capacity {
+            entries.remove_oldest();
+        }
+        entries.insert(key, rows);
+    }
+}
+
+/// Wraps clients into a `CachedClient` with `config`.
+#[derive(Debug, Clone, Default)]
+pub struct CacheLayer {
+    pub config: CacheConfig,
+}
+
+impl CacheLayer {
+    pub fn new(config: CacheConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl<C: Client> Layer<C> for CacheLayer {
+    type Client = CachedClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        CachedClient::with_config(inner, self.config.clone())
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for CachedClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        let result = self.inner.create(table, item).await;
+        self.invalidate_table(table);
+        result
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        let mut founds = vec![];
+        let mut misses = vec![];
+        for id in ids {
+            let id = serde_json::to_value(id)?;
+            let cache_key = (table.to_string(), key.to_string(), id.to_string());
+            match self.get(&cache_key) {
+                Some(rows) => founds.extend(rows),
+                None => misses.push(id),
+            }
+        }
+        if misses.is_empty() {
+            return Ok(founds);
+        }
+
+        let generation = self.generation(table);
+        let rows = self.inner.find_by_keys(table, key, misses).await?;
+        let mut fetched: HashMap<String, Vec<serde_json::Value>> = HashMap::new();
+        for row in &rows {
+            if let Some(id) = row.get(key) {
+                fetched.entry(id.to_string()).or_default().push(row.clone());
+            }
+        }
+        for (id, rows) in fetched {
+            self.insert((table.to_string(), key.to_string(), id), rows, generation);
+        }
+        founds.extend(rows);
+        Ok(founds)
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner
+            .find_by_keys_select(table, key, ids, columns)
+            .await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_range(table, key, after, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner
+            .find_where_rows(table, filters, order, limit)
+            .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_range_rows(table, key, after, limit).await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        let result = self.inner.update_by_keys(table, key, items).await;
+        self.invalidate_table(table);
+        result
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        let result = self.inner.delete_by_keys(table, key, ids).await;
+        self.invalidate_table(table);
+        result
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        let result = self.inner.update_where(table, filters, item).await;
+        self.invalidate_table(table);
+        result
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        let tables = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Create { table, .. }
+                | Operation::Update { table, .. }
+                | Operation::Delete { table, .. } => table.clone(),
+            })
+            .collect::<Vec<_>>();
+        let result = self.inner.execute_batch(operations).await;
+        for table in tables {
+            self.invalidate_table(&table);
+        }
+        result
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use serde_json::json;
+
+    async fn cached_client(config: CacheConfig) -> CachedClient<MemoryClient> {
+        let memory = MemoryClient::default();
+        for id in 1..=3 {
+            memory
+                .create("users", &json!({ "id": id, "name": format!("user{}", id) }))
+                .await
+                .unwrap();
+        }
+        CachedClient::with_config(memory, config)
+    }
+
+    fn reads(client: &CachedClient<MemoryClient>) -> usize {
+        client
+            .inner()
+            .calls()
+            .iter()
+            .filter(|call| call.starts_with("find_by_keys"))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn caches_reads_per_id() {
+        let client = cached_client(CacheConfig::default()).await;
+        assert_eq!(
+            client
+                .find_by_keys("users", "id", vec![1, 2])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            client
+                .find_by_keys("users", "id", vec![2, 3])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            client
+                .find_by_keys("users", "id", vec![1, 3])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(reads(&client), 2);
+        assert_eq!(
+            client.inner().calls().last().unwrap(),
+            "find_by_keys users id 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn invalidates_table_on_writes() {
+        let client = cached_client(CacheConfig::default()).await;
+        client.find_by_keys("users", "id", vec![1]).await.unwrap();
+        client
+            .update_by_keys("users", "id", vec![("1", json!({ "name": "renamed" }))])
+            .await
+            .unwrap();
+        assert!(client.is_empty());
+        let founds = client.find_by_keys("users", "id", vec![1]).await.unwrap();
+        assert_eq!(founds[0]["name"], "renamed");
+        assert_eq!(reads(&client), 2);
+    }
+
+    #[tokio::test]
+    async fn skips_reads_overtaken_by_writes() {
+        let client = cached_client(CacheConfig::default()).await;
+        let key = ("users".to_string(), "id".to_string(), "1".to_string());
+        let generation = client.generation("users");
+        client.delete_by_keys("users", "id", vec![1]).await.unwrap();
+        client.insert(key.clone(), vec![json!({ "id": 1 })], generation);
+        assert!(client.is_empty());
+
+        client.insert(key, vec![json!({ "id": 1 })], client.generation("users"));
+        assert_eq!(client.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn expires_and_evicts_entries() {
+        let config = CacheConfig {
+            ttl: Duration::ZERO,
+            max_capacity: 2,
+        };
+        let client = cached_client(config).await;
+        client.find_by_keys("users", "id", vec![1]).await.unwrap();
+        client.find_by_keys("users", "id", vec![1]).await.unwrap();
+        assert_eq!(reads(&client), 2);
+
+        let client = cached_client(CacheConfig {
+            max_capacity: 2,
+            ..Default::default()
+        })
+        .await;
+        client
+            .find_by_keys("users", "id", vec![1, 2, 3])
+            .await
+            .unwrap();
+        assert_eq!(client.len(), 2);
+        client.find_by_keys("users", "id", vec![3]).await.unwrap();
+        assert_eq!(reads(&client), 1);
+        client.find_by_keys("users", "id", vec![1]).await.unwrap();
+        assert_eq!(reads(&client), 2);
+    }
+}
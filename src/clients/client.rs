@@ -1,9 +1,17 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::clients::clock::{Clock, SystemClock};
+use crate::clients::transaction::{Operation, Transaction};
+use crate::entity::column::FilterOp;
+use crate::entity::row::EntityRow;
 
 #[async_trait]
 pub trait Client: Send + Sync + 'static {
+    /// Representation of the rows returned by `find_rows`, which entities are decoded from.
+    type Row: EntityRow;
+
     async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()>;
 
     async fn find_by_keys<K: Serialize + Send + Sync>(
@@ -13,6 +21,125 @@ pub trait Client: Send + Sync + 'static {
         ids: Vec<K>,
     ) -> Result<Vec<serde_json::Value>>;
 
+    /// Like `find_by_keys`, but only returns `columns` of each row.
+    /// The default implementation fetches whole rows and drops the other columns.
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        let founds = self.find_by_keys(table, key, ids).await?;
+        Ok(founds
+            .into_iter()
+            .map(|value| match value {
+                serde_json::Value::Object(mut row) => {
+                    row.retain(|column, _| columns.contains(&column.as_str()));
+                    serde_json::Value::Object(row)
+                }
+                value => value,
+            })
+            .collect())
+    }
+
+    /// Like `find_by_keys`, but returns the rows as `Row`s.
+    /// The default implementation converts the rows of `find_by_keys`.
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        let founds = self.find_by_keys(table, key, ids).await?;
+        Ok(founds
+            .into_iter()
+            .map(Self::Row::from_json)
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Like `find_by_keys`, but deserializes the rows into `T`.
+    /// The default implementation converts the rows of `find_by_keys`; backends reading
+    /// JSON bodies deserialize them straight into `T` instead.
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        let founds = self.find_by_keys(table, key, ids).await?;
+        Ok(founds
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<T>, _>>()?)
+    }
+
+    /// Returns at most `limit` rows of `table` ordered by `key`, starting after the row
+    /// whose key is `after`, for paging through tables too large to read at once.
+    /// Backends without paging keep the default, which rejects the read.
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        let _ = (key, after, limit);
+        bail!(
+            "find_range is not supported by this client, table: {}",
+            table
+        )
+    }
+
+    /// Returns the rows of `table` matching every `(column, op, value)` of `filters`,
+    /// sorted by the `(column, ascending)` pairs of `order`, at most `limit` of them.
+    /// Backends without filtered reads keep the default, which rejects the read.
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        let _ = (filters, order, limit);
+        bail!(
+            "find_where is not supported by this client, table: {}",
+            table
+        )
+    }
+
+    /// Like `find_where`, but returns the rows as `Row`s.
+    /// The default implementation converts the rows of `find_where`.
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        let founds = self.find_where(table, filters, order, limit).await?;
+        Ok(founds
+            .into_iter()
+            .map(Self::Row::from_json)
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Like `find_range`, but returns the rows as `Row`s.
+    /// The default implementation converts the rows of `find_range`.
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        let founds = self.find_range(table, key, after, limit).await?;
+        Ok(founds
+            .into_iter()
+            .map(Self::Row::from_json)
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
     async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
         &self,
         table: &str,
@@ -29,6 +156,54 @@ pub trait Client: Send + Sync + 'static {
         ids: Vec<K>,
     ) -> Result<()>;
 
+    /// Updates the rows whose columns equal every `(column, value)` of `filters` with
+    /// `item`, and returns how many rows were updated.
+    /// Backends without conditional updates keep the default, which rejects the update.
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        let _ = (filters, item);
+        bail!(
+            "update_where is not supported by this client, table: {}",
+            table
+        )
+    }
+
+    /// Applies `operations` atomically, all or nothing.
+    /// Backends without transactional support keep the default, which rejects the batch.
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        bail!(
+            "execute_batch is not supported by this client, {} operations rejected",
+            operations.len()
+        )
+    }
+
+    fn begin(&self) -> Transaction<'_, Self>
+    where
+        Self: Sized,
+    {
+        Transaction::new(self)
+    }
+
+    /// Runs `f` against a new transaction and commits it, or drops it if `f` fails.
+    async fn transaction<F>(&self, f: F) -> Result<()>
+    where
+        Self: Sized,
+        F: FnOnce(&mut Transaction<'_, Self>) -> Result<()> + Send,
+    {
+        let mut tx = self.begin();
+        f(&mut tx)?;
+        tx.commit().await
+    }
+
+    /// Clock of the `created_at` and `updated_at` columns written through this client.
+    fn clock(&self) -> &dyn Clock {
+        &SystemClock
+    }
+
     fn as_str<T: Serialize>(&self, v: T) -> String {
         serde_json::json!(v).to_string()
     }
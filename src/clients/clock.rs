@@ -0,0 +1,224 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::clients::client::Client;
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+
+/// Source of the time written to `created_at` and `updated_at` columns.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system time, used by clients by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl<K: Clock + ?Sized> Clock for Arc<K> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+/// A clock standing still until it is `set` or `advance`d, for tests.
+#[derive(Debug)]
+pub struct FixedClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Replaces the clock of the inner client, e.g. with a `FixedClock` in tests.
+pub struct ClockedClient<C: Client, K: Clock> {
+    inner: C,
+    clock: K,
+}
+
+impl<C: Client, K: Clock> ClockedClient<C, K> {
+    pub fn new(inner: C, clock: K) -> Self {
+        Self { inner, clock }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+/// Wraps clients into a `ClockedClient` reading `clock`.
+#[derive(Debug, Clone)]
+pub struct ClockLayer<K> {
+    pub clock: K,
+}
+
+impl<K: Clock> ClockLayer<K> {
+    pub fn new(clock: K) -> Self {
+        Self { clock }
+    }
+}
+
+impl<C: Client, K: Clock + Clone> Layer<C> for ClockLayer<K> {
+    type Client = ClockedClient<C, K>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        ClockedClient::new(inner, self.clock.clone())
+    }
+}
+
+#[async_trait]
+impl<C: Client, K: Clock> Client for ClockedClient<C, K> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        self.inner.create(table, item).await
+    }
+
+    async fn find_by_keys<K2: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K2>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_by_keys(table, key, ids).await
+    }
+
+    async fn find_by_keys_select<K2: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K2>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner
+            .find_by_keys_select(table, key, ids, columns)
+            .await
+    }
+
+    async fn find_rows<K2: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K2>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_rows(table, key, ids).await
+    }
+
+    async fn find_by_keys_typed<K2: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K2>,
+    ) -> Result<Vec<T>> {
+        self.inner.find_by_keys_typed(table, key, ids).await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_range(table, key, after, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner
+            .find_where_rows(table, filters, order, limit)
+            .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_range_rows(table, key, after, limit).await
+    }
+
+    async fn update_by_keys<K2: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K2, T)>,
+    ) -> Result<()>
+    where
+        K2: ToString + AsRef<str>,
+    {
+        self.inner.update_by_keys(table, key, items).await
+    }
+
+    async fn delete_by_keys<K2: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K2>,
+    ) -> Result<()> {
+        self.inner.delete_by_keys(table, key, ids).await
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        self.inner.update_where(table, filters, item).await
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        self.inner.execute_batch(operations).await
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &self.clock
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::error::StatusError;
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+
+pub const DEFAULT_WINDOW: Duration = Duration::from_millis(5);
+
+type BatchKey = (String, String);
+
+type BatchResult = Result<Arc<Vec<serde_json::Value>>, Arc<anyhow::Error>>;
+
+#[derive(Default)]
+struct Batch {
+    ids: Vec<serde_json::Value>,
+    waiters: Vec<oneshot::Sender<BatchResult>>,
+}
+
+/// Merges concurrent `find_by_keys` calls on the same table and key that arrive within
+/// `window` into one call with all ids, then hands every caller its own rows.
+///
+/// The first caller of a window leads the batch; the others wait for it and fall back to
+/// their own call if it is cancelled. Other operations pass through.
+pub struct CoalescingClient<C: Client> {
+    inner: C,
+    window: Duration,
+    pending: Mutex<HashMap<BatchKey, Batch>>,
+}
+
+impl<C: Client> CoalescingClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self::with_window(inner, DEFAULT_WINDOW)
+    }
+
+    pub fn with_window(inner: C, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    async fn lead(&self, batch_key: &BatchKey) {
+        let mut guard = LeaderGuard {
+            pending: &self.pending,
+            batch_key,
+            armed: true,
+        };
+        tokio::time::sleep(self.window).await;
+        let batch = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(batch_key)
+            .unwrap_or_default();
+        guard.armed = false;
+
+        let mut seen = HashSet::new();
+        let ids = batch
+            .ids
+            .into_iter()
+            .filter(|id| seen.insert(id.to_string()))
+            .collect::<Vec<_>>();
+        debug!(
+            "CoalescingClient.find_by_keys: {}, callers: {}, ids: {}",
+            batch_key.0,
+            batch.waiters.len(),
+            ids.len()
+        );
+        let result = self
+            .inner
+            .find_by_keys(&batch_key.0, &batch_key.1, ids)
+            .await
+            .map(Arc::new)
+            .map_err(Arc::new);
+        for waiter in batch.waiters {
+            let _ = waiter.send(result.clone());
+        }
+    }
+}
+
+/// Drops the pending batch if the leader is cancelled before running it, so that the
+/// waiters are woken up instead of hanging.
+struct LeaderGuard<'a> {
+    pending: &'a Mutex<HashMap<BatchKey, Batch>>,
+    batch_key: &'a BatchKey,
+    armed: bool,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.remove(self.batch_key);
+            }
+        }
+    }
+}
+
+/// Rebuilds an owned error from a shared one, keeping a `StatusError` downcastable.
+fn to_owned_error(error: &anyhow::Error) -> anyhow::Error {
+    let message = format!("{:#}", error);
+    match error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<StatusError>())
+    {
+        Some(status) => anyhow::Error::new(status.clone()).context(message),
+        None => anyhow!(message),
+    }
+}
+
+/// Wraps clients into a `CoalescingClient` with `window`.
+#[derive(Debug, Clone)]
+pub struct CoalesceLayer {
+    pub window: Duration,
+}
+
+impl Default for CoalesceLayer {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+impl<C: Client> Layer<C> for CoalesceLayer {
+    type Client = CoalescingClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        CoalescingClient::with_window(inner, self.window)
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for CoalescingClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        self.inner.create(table, item).await
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        let ids = ids
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch_key = (table.to_string(), key.to_string());
+        let (sender, receiver) = oneshot::channel();
+        let leader = {
+            let mut pending = self.pending.lock().unwrap();
+            let leader = !pending.contains_key(&batch_key);
+            let batch = pending.entry(batch_key.clone()).or_default();
+            batch.ids.extend(ids.iter().cloned());
+            batch.waiters.push(sender);
+            leader
+        };
+        if leader {
+            self.lead(&batch_key).await;
+        }
+
+        let rows = match receiver.await {
+            Ok(Ok(rows)) => rows,
+            Ok(Err(e)) => return Err(to_owned_error(&e)),
+            Err(_) => return self.inner.find_by_keys(table, key, ids).await,
+        };
+        let ids = ids.iter().map(|id| id.to_string()).collect::<HashSet<_>>();
+        Ok(rows
+            .iter()
+            .filter(|row| {
+                row.get(key)
+                    .is_some_and(|value| ids.contains(&value.to_string()))
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner
+            .find_by_keys_select(table, key, ids, columns)
+            .await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_range(table, key, after, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner
+            .find_where_rows(table, filters, order, limit)
+            .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_range_rows(table, key, after, limit).await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        self.inner.update_by_keys(table, key, items).await
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        self.inner.delete_by_keys(table, key, ids).await
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        self.inner.update_where(table, filters, item).await
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        self.inner.execute_batch(operations).await
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use serde_json::json;
+
+    async fn coalescing_client() -> CoalescingClient<MemoryClient> {
+        let memory = MemoryClient::default();
+        for id in 1..=3 {
+            memory.create("users", &json!({ "id": id })).await.unwrap();
+        }
+        memory.create("orders", &json!({ "id": 1 })).await.unwrap();
+        CoalescingClient::new(memory)
+    }
+
+    #[tokio::test]
+    async fn merges_concurrent_reads() {
+        let client = coalescing_client().await;
+        let (a, b, c, d) = tokio::join!(
+            client.find_by_keys("users", "id", vec![1]),
+            client.find_by_keys("users", "id", vec![2, 3]),
+            client.find_by_keys("users", "id", vec![1, 4]),
+            client.find_by_keys("orders", "id", vec![1]),
+        );
+        assert_eq!(a.unwrap(), vec![json!({ "id": 1 })]);
+        assert_eq!(b.unwrap(), vec![json!({ "id": 2 }), json!({ "id": 3 })]);
+        assert_eq!(c.unwrap(), vec![json!({ "id": 1 })]);
+        assert_eq!(d.unwrap(), vec![json!({ "id": 1 })]);
+
+        let mut calls = client.inner().calls();
+        calls.sort();
+        assert_eq!(
+            calls[calls.len() - 2..],
+            ["find_by_keys orders id 1", "find_by_keys users id 4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn waiters_fall_back_when_leader_is_cancelled() {
+        let client = coalescing_client().await;
+        let leader = client.find_by_keys("users", "id", vec![1]);
+        let follower = client.find_by_keys("users", "id", vec![2]);
+        let (leader, follower) = tokio::join!(
+            tokio::time::timeout(Duration::from_millis(1), leader),
+            follower
+        );
+        assert!(leader.is_err());
+        assert_eq!(follower.unwrap(), vec![json!({ "id": 2 })]);
+    }
+}
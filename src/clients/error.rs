@@ -0,0 +1,75 @@
+use std::fmt;
+use std::time::Duration;
+
+/// A non-success HTTP response, kept in the `anyhow::Error` chain so wrappers like
+/// `RetryingClient` can inspect it with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusError {
+    pub status: u16,
+    /// Parsed from a `Retry-After` header given in seconds.
+    pub retry_after: Option<Duration>,
+}
+
+impl StatusError {
+    pub fn from_response(response: &reqwest::Response) -> Self {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        Self {
+            status: response.status().as_u16(),
+            retry_after,
+        }
+    }
+
+    /// 429 and the gateway errors, which are worth retrying.
+    pub fn is_transient(&self) -> bool {
+        matches!(self.status, 429 | 502 | 503 | 504)
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request failed with status: {}", self.status)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// An update rejected because the row was changed since it was read, i.e. its version
+/// column no longer holds the expected value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub table: String,
+    pub id: String,
+    pub version: i64,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Conflict on {} {}, version {} is outdated",
+            self.table, self.id, self.version
+        )
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// Auditing of a write that the inner client already applied failed, e.g. because the
+/// sink was unreachable. Retrying the call would apply the write a second time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditFailed {
+    pub table: String,
+}
+
+impl fmt::Display for AuditFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Auditing a committed write to {} failed", self.table)
+    }
+}
+
+impl std::error::Error for AuditFailed {}
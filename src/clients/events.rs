@@ -0,0 +1,523 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::ids::{id_string, plain_id, plain_ids};
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+use crate::entity::extend::ExtendedCrud;
+
+/// Key column of tables whose key was not given to `EventClient::key`.
+pub const DEFAULT_KEY: &str = "id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A successful write of one row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityChanged {
+    pub table: String,
+    /// Key of the changed row in the column the write named, a string for the string ids
+    /// of `update_by_keys` and `update_where`. For `update_where`, the filter on the key column of the table,
+    /// `Null` when it does not filter on it.
+    pub key: serde_json::Value,
+    pub op: ChangeOp,
+    /// The created row, the written columns of updates, `Null` for deletes.
+    pub payload: serde_json::Value,
+}
+
+/// Publishes an `EntityChanged` on a broadcast channel after every successful write.
+///
+/// Events are sent once the inner client returned, so failed writes publish nothing;
+/// sending without subscribers is not an error. `update_where` only publishes when it
+/// matched. Updates and deletes by key publish for every id given, unless `only_existing`
+/// is set.
+pub struct EventClient<C: Client> {
+    inner: C,
+    sender: broadcast::Sender<EntityChanged>,
+    keys: HashMap<String, String>,
+    only_existing: bool,
+}
+
+impl<C: Client> EventClient<C> {
+    pub fn new(inner: C, sender: broadcast::Sender<EntityChanged>) -> Self {
+        Self {
+            inner,
+            sender,
+            keys: HashMap::new(),
+            only_existing: false,
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EntityChanged> {
+        self.sender.subscribe()
+    }
+
+    /// Reads the key of rows created or updated with `update_where` in `table` from
+    /// `column` instead of `DEFAULT_KEY`.
+    pub fn key(mut self, table: &str, column: &str) -> Self {
+        self.keys.insert(table.to_string(), column.to_string());
+        self
+    }
+
+    /// Reads which ids have rows before updates and deletes by key, so ids without rows
+    /// publish nothing. Costs one read per write, and one per table of a batch.
+    pub fn only_existing(mut self) -> Self {
+        self.only_existing = true;
+        self
+    }
+
+    /// The ids among `ids` of rows currently in `table`, by `id_string`, or `None` when
+    /// `only_existing` is not set.
+    async fn existing(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<serde_json::Value>,
+    ) -> Result<Option<HashSet<String>>> {
+        if !self.only_existing {
+            return Ok(None);
+        }
+        if ids.is_empty() {
+            return Ok(Some(HashSet::new()));
+        }
+        let rows = self
+            .inner
+            .find_by_keys_select(table, key, ids, &[key])
+            .await
+            .context("EventClient.existing failed")?;
+        Ok(Some(
+            rows.iter()
+                .filter_map(|row| row.get(key).map(id_string))
+                .collect(),
+        ))
+    }
+
+    fn publish(
+        &self,
+        table: &str,
+        key: serde_json::Value,
+        op: ChangeOp,
+        payload: serde_json::Value,
+    ) {
+        let _ = self.sender.send(EntityChanged {
+            table: table.to_string(),
+            key,
+            op,
+            payload,
+        });
+    }
+
+    fn created(&self, table: &str, item: serde_json::Value) {
+        let _ = self.sender.send(changed(
+            Operation::Create {
+                table: table.to_string(),
+                item,
+            },
+            &self.keys,
+        ));
+    }
+}
+
+/// The event of an applied `operation`, reading the key of created rows from `keys`.
+pub(crate) fn changed(operation: Operation, keys: &HashMap<String, String>) -> EntityChanged {
+    match operation {
+        Operation::Create { table, item } => {
+            let column = keys.get(&table).map_or(DEFAULT_KEY, String::as_str);
+            EntityChanged {
+                key: item.get(column).cloned().unwrap_or_default(),
+                table,
+                op: ChangeOp::Create,
+                payload: item,
+            }
+        }
+        Operation::Update {
+            table, id, item, ..
+        } => EntityChanged {
+            table,
+            key: id,
+            op: ChangeOp::Update,
+            payload: item,
+        },
+        Operation::Delete { table, id, .. } => EntityChanged {
+            table,
+            key: id,
+            op: ChangeOp::Delete,
+            payload: serde_json::Value::Null,
+        },
+    }
+}
+
+/// Wraps clients into an `EventClient` sharing one channel, so every client built from
+/// the layer publishes to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct EventLayer {
+    sender: broadcast::Sender<EntityChanged>,
+    keys: HashMap<String, String>,
+    only_existing: bool,
+}
+
+impl EventLayer {
+    /// Subscribers lagging more than `capacity` events behind miss the oldest ones.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+            keys: HashMap::new(),
+            only_existing: false,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EntityChanged> {
+        self.sender.subscribe()
+    }
+
+    /// Reads the key of rows created or updated with `update_where` in `table` from
+    /// `column` instead of `DEFAULT_KEY`.
+    pub fn key(mut self, table: &str, column: &str) -> Self {
+        self.keys.insert(table.to_string(), column.to_string());
+        self
+    }
+
+    /// Sets `EventClient::only_existing` on the clients built from the layer.
+    pub fn only_existing(mut self) -> Self {
+        self.only_existing = true;
+        self
+    }
+
+    /// Reads the key of created `E` rows from its primary key.
+    pub fn entity<C: Client, E: ExtendedCrud<C>>(self) -> Self {
+        self.key(E::TABLE_NAME, E::PRIMARY_KEY_NAME)
+    }
+}
+
+impl<C: Client> Layer<C> for EventLayer {
+    type Client = EventClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        EventClient {
+            inner,
+            sender: self.sender.clone(),
+            keys: self.keys.clone(),
+            only_existing: self.only_existing,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for EventClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        self.inner.create(table, item).await?;
+        self.created(table, serde_json::to_value(item)?);
+        Ok(())
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_by_keys(table, key, ids).await
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner
+            .find_by_keys_select(table, key, ids, columns)
+            .await
+    }
+
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_rows(table, key, ids).await
+    }
+
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        self.inner.find_by_keys_typed(table, key, ids).await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_range(table, key, after, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner
+            .find_where_rows(table, filters, order, limit)
+            .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_range_rows(table, key, after, limit).await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        let changes = items
+            .iter()
+            .map(|(id, item)| Ok((plain_id(id.as_ref()), serde_json::to_value(item)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let existing = self
+            .existing(
+                table,
+                key,
+                changes.iter().map(|(id, _)| id.clone()).collect(),
+            )
+            .await?;
+        self.inner.update_by_keys(table, key, items).await?;
+        for (id, item) in changes {
+            if existing
+                .as_ref()
+                .is_none_or(|e| e.contains(&id_string(&id)))
+            {
+                self.publish(table, id, ChangeOp::Update, item);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        let keys = plain_ids(&ids)?;
+        let existing = self.existing(table, key, keys.clone()).await?;
+        self.inner.delete_by_keys(table, key, ids).await?;
+        for id in keys {
+            if existing
+                .as_ref()
+                .is_none_or(|e| e.contains(&id_string(&id)))
+            {
+                self.publish(table, id, ChangeOp::Delete, serde_json::Value::Null);
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        let updated = self.inner.update_where(table, filters, item).await?;
+        if updated > 0 {
+            let column = self.keys.get(table).map_or(DEFAULT_KEY, String::as_str);
+            let key = filters
+                .iter()
+                .find(|(filter, _)| *filter == column)
+                .map(|(_, id)| plain_id(id))
+                .unwrap_or_default();
+            self.publish(table, key, ChangeOp::Update, serde_json::to_value(item)?);
+        }
+        Ok(updated)
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        let mut ids: HashMap<(&str, &str), Vec<serde_json::Value>> = HashMap::new();
+        for operation in &operations {
+            if let Operation::Update { table, key, id, .. }
+            | Operation::Delete { table, key, id, .. } = operation
+            {
+                ids.entry((table, key)).or_default().push(id.clone());
+            }
+        }
+        let mut existing = HashMap::new();
+        for ((table, key), ids) in ids {
+            existing.insert((table, key), self.existing(table, key, ids).await?);
+        }
+        let applied = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Create { .. } => true,
+                Operation::Update { table, key, id, .. }
+                | Operation::Delete { table, key, id, .. } => existing
+                    .get(&(table.as_str(), key.as_str()))
+                    .and_then(Option::as_ref)
+                    .is_none_or(|e| e.contains(&id_string(id))),
+            })
+            .collect::<Vec<_>>();
+        self.inner.execute_batch(operations.clone()).await?;
+        for (operation, applied) in operations.into_iter().zip(applied) {
+            if applied {
+                let _ = self.sender.send(changed(operation, &self.keys));
+            }
+        }
+        Ok(())
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use serde_json::json;
+
+    /// Writes rows 1 and 2 of `users`, given only row 1 exists, and an order in a batch.
+    async fn write(client: &EventClient<MemoryClient>) {
+        client
+            .create("users", &json!({ "id": 1, "name": "john" }))
+            .await
+            .unwrap();
+        client
+            .update_by_keys("users", "id", vec![("1", json!({ "name": "jane" }))])
+            .await
+            .unwrap();
+        client
+            .update_by_keys("users", "id", vec![("2", json!({ "name": "jim" }))])
+            .await
+            .unwrap();
+        client
+            .update_where(
+                "users",
+                &[("name", "jane".to_string())],
+                &json!({ "age": 3 }),
+            )
+            .await
+            .unwrap();
+        client
+            .delete_by_keys("users", "id", vec![1, 2])
+            .await
+            .unwrap();
+        client
+            .execute_batch(vec![
+                Operation::Create {
+                    table: "orders".to_string(),
+                    item: json!({ "order_id": 7 }),
+                },
+                Operation::Delete {
+                    table: "orders".to_string(),
+                    key: "order_id".to_string(),
+                    id: json!(8),
+                },
+            ])
+            .await
+            .unwrap();
+    }
+
+    fn summary(
+        receiver: &mut broadcast::Receiver<EntityChanged>,
+    ) -> Vec<(String, serde_json::Value, ChangeOp)> {
+        std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|event| (event.table, event.key, event.op))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn publishes_successful_writes() {
+        let layer = EventLayer::new(16).key("orders", "order_id");
+        let mut receiver = layer.subscribe();
+        let client = layer.layer(MemoryClient::default());
+
+        write(&client).await;
+        let event = |table: &str, key, op| (table.to_string(), key, op);
+        assert_eq!(
+            summary(&mut receiver),
+            [
+                event("users", json!(1), ChangeOp::Create),
+                event("users", json!("1"), ChangeOp::Update),
+                event("users", json!("2"), ChangeOp::Update),
+                event("users", json!(null), ChangeOp::Update),
+                event("users", json!(1), ChangeOp::Delete),
+                event("users", json!(2), ChangeOp::Delete),
+                event("orders", json!(7), ChangeOp::Create),
+                event("orders", json!(8), ChangeOp::Delete),
+            ]
+        );
+        let reads = client.inner().calls().into_iter();
+        assert_eq!(reads.filter(|call| call.starts_with("find")).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn publishes_only_existing_rows_when_asked() {
+        let layer = EventLayer::new(16)
+            .key("orders", "order_id")
+            .only_existing();
+        let mut receiver = layer.subscribe();
+        let client = layer.layer(MemoryClient::default());
+
+        write(&client).await;
+        let event = |table: &str, key, op| (table.to_string(), key, op);
+        assert_eq!(
+            summary(&mut receiver),
+            [
+                event("users", json!(1), ChangeOp::Create),
+                event("users", json!("1"), ChangeOp::Update),
+                event("users", json!(null), ChangeOp::Update),
+                event("users", json!(1), ChangeOp::Delete),
+                event("orders", json!(7), ChangeOp::Create),
+            ]
+        );
+    }
+}
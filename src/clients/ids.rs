@@ -0,0 +1,27 @@
+//! Conversions between the string ids of `update_by_keys` and `update_where` and the JSON
+//! ids of rows, shared by the clients that record or publish writes.
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// The JSON id of an id given as a string, kept a string even when it looks like a
+/// number, as the type of the key column is unknown.
+pub(crate) fn plain_id(id: &str) -> serde_json::Value {
+    serde_json::Value::String(id.to_string())
+}
+
+/// The JSON ids of `ids`, numeric only when the ids are.
+pub(crate) fn plain_ids<K: Serialize>(ids: &[K]) -> Result<Vec<serde_json::Value>> {
+    Ok(ids
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<_, _>>()?)
+}
+
+/// The string form `update_by_keys` expects for `id`.
+pub(crate) fn id_string(id: &serde_json::Value) -> String {
+    match id {
+        serde_json::Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
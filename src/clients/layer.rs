@@ -0,0 +1,61 @@
+use crate::clients::client::Client;
+
+/// Wraps a `Client` into another `Client` delegating to it, e.g. to add retries,
+/// logging or headers around any backend.
+pub trait Layer<C: Client> {
+    type Client: Client;
+
+    fn layer(&self, inner: C) -> Self::Client;
+}
+
+/// Stacks layers around a backend. Each `layer` call wraps everything added before,
+/// so the last layer is the outermost one and sees every call first.
+///
+/// ```ignore
+/// let client = ClientBuilder::new(supabase)
+///     .layer(logging)
+///     .layer(RetryLayer::default())
+///     .build();
+/// ```
+pub struct ClientBuilder<C: Client> {
+    client: C,
+}
+
+impl<C: Client> ClientBuilder<C> {
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+
+    pub fn layer<L: Layer<C>>(self, layer: L) -> ClientBuilder<L::Client> {
+        ClientBuilder {
+            client: layer.layer(self.client),
+        }
+    }
+
+    pub fn build(self) -> C {
+        self.client
+    }
+}
+
+/// A `Layer` made from a closure, see `layer_fn`.
+#[derive(Clone)]
+pub struct LayerFn<F> {
+    f: F,
+}
+
+pub fn layer_fn<F>(f: F) -> LayerFn<F> {
+    LayerFn { f }
+}
+
+impl<C, W, F> Layer<C> for LayerFn<F>
+where
+    C: Client,
+    W: Client,
+    F: Fn(C) -> W,
+{
+    type Client = W;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        (self.f)(inner)
+    }
+}
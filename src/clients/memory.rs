@@ -0,0 +1,281 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::clients::client::Client;
+use crate::clients::error::Conflict;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+
+/// In-memory `Client` recording every call, for the tests of wrappers and entities.
+#[derive(Default)]
+pub(crate) struct MemoryClient {
+    pub tables: Mutex<HashMap<String, Vec<serde_json::Value>>>,
+    pub calls: Mutex<Vec<String>>,
+}
+
+impl MemoryClient {
+    pub fn rows(&self, table: &str) -> Vec<serde_json::Value> {
+        self.tables
+            .lock()
+            .unwrap()
+            .get(table)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+/// Compares a stored column with an id, which callers pass either as a JSON value or as
+/// its string form.
+fn matches(column: Option<&serde_json::Value>, id: &serde_json::Value) -> bool {
+    let Some(column) = column else {
+        return false;
+    };
+    if column == id {
+        return true;
+    }
+    match id {
+        serde_json::Value::String(s) => {
+            column.as_str() == Some(s.as_str())
+                || serde_json::from_str::<serde_json::Value>(s).ok().as_ref() == Some(column)
+        }
+        _ => false,
+    }
+}
+
+/// Orders stored column values, numbers by value and anything else by its JSON form.
+fn compare(a: Option<&serde_json::Value>, b: Option<&serde_json::Value>) -> Ordering {
+    match (a, b) {
+        (Some(serde_json::Value::Number(a)), Some(serde_json::Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (a, b) => a.map(|a| a.to_string()).cmp(&b.map(|b| b.to_string())),
+    }
+}
+
+#[async_trait]
+impl Client for MemoryClient {
+    type Row = serde_json::Value;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        self.record(format!("create {}", table));
+        let item = serde_json::to_value(item)?;
+        self.tables
+            .lock()
+            .unwrap()
+            .entry(table.to_string())
+            .or_default()
+            .push(item);
+        Ok(())
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.record(format!("find_by_keys {} {} {}", table, key, ids.len()));
+        let ids = ids
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self
+            .rows(table)
+            .into_iter()
+            .filter(|row| ids.iter().any(|id| matches(row.get(key), id)))
+            .collect())
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.record(format!("find_where {} {}", table, filters.len()));
+        let mut rows = self
+            .rows(table)
+            .into_iter()
+            .filter(|row| {
+                filters.iter().all(|(column, op, value)| {
+                    let column = row.get(*column).filter(|v| !v.is_null());
+                    let ordering = || column.map(|column| compare(Some(column), Some(value)));
+                    match op {
+                        FilterOp::Eq if value.is_null() => column.is_none(),
+                        FilterOp::Neq if value.is_null() => column.is_some(),
+                        FilterOp::Eq => matches(column, value),
+                        FilterOp::Neq => column.is_some() && !matches(column, value),
+                        FilterOp::Gt => ordering().is_some_and(Ordering::is_gt),
+                        FilterOp::Gte => ordering().is_some_and(Ordering::is_ge),
+                        FilterOp::Lt => ordering().is_some_and(Ordering::is_lt),
+                        FilterOp::Lte => ordering().is_some_and(Ordering::is_le),
+                        FilterOp::In => value
+                            .as_array()
+                            .is_some_and(|values| values.iter().any(|v| matches(column, v))),
+                        FilterOp::IsNull => column.is_none(),
+                        FilterOp::NotNull => column.is_some(),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for (column, ascending) in order.iter().rev() {
+            rows.sort_by(|a, b| {
+                let ordering = compare(a.get(*column), b.get(*column));
+                if *ascending {
+                    ordering
+                } else {
+                    ordering.reverse()
+                }
+            });
+        }
+        Ok(rows.into_iter().take(limit.unwrap_or(usize::MAX)).collect())
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        let cursor = after.map_or("-".to_string(), |after| after.to_string());
+        self.record(format!("find_range {} {} {}", table, cursor, limit));
+        let mut rows = self.rows(table);
+        if let Some(after) = after {
+            rows.retain(|row| compare(row.get(key), Some(after)).is_gt());
+        }
+        rows.sort_by(|a, b| compare(a.get(key), b.get(key)));
+        Ok(rows.into_iter().take(limit).collect())
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        self.record(format!("update_by_keys {} {}", table, items.len()));
+        let mut tables = self.tables.lock().unwrap();
+        let rows = tables.entry(table.to_string()).or_default();
+        for (id, item) in items {
+            let id = serde_json::Value::String(id.as_ref().to_string());
+            let item = serde_json::to_value(item)?;
+            let changes = item
+                .as_object()
+                .ok_or_else(|| anyhow!("MemoryClient.update_by_keys, item must be an object"))?;
+            for row in rows.iter_mut().filter(|row| matches(row.get(key), &id)) {
+                let row = row.as_object_mut().unwrap();
+                for (column, value) in changes {
+                    row.insert(column.clone(), value.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        self.record(format!("delete_by_keys {} {}", table, ids.len()));
+        let ids = ids
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.tables
+            .lock()
+            .unwrap()
+            .entry(table.to_string())
+            .or_default()
+            .retain(|row| !ids.iter().any(|id| matches(row.get(key), id)));
+        Ok(())
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        self.record(format!("update_where {} {}", table, filters.len()));
+        let item = serde_json::to_value(item)?;
+        let changes = item
+            .as_object()
+            .ok_or_else(|| anyhow!("MemoryClient.update_where, item must be an object"))?;
+        let mut tables = self.tables.lock().unwrap();
+        let mut updated = 0;
+        for row in tables.entry(table.to_string()).or_default() {
+            let matched = filters.iter().all(|(column, value)| {
+                matches(row.get(*column), &serde_json::Value::String(value.clone()))
+            });
+            if matched {
+                let row = row.as_object_mut().unwrap();
+                for (column, value) in changes {
+                    row.insert(column.clone(), value.clone());
+                }
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        self.record(format!("execute_batch {}", operations.len()));
+        for operation in operations {
+            match operation {
+                Operation::Create { table, item } => self.create(&table, &item).await?,
+                Operation::Update {
+                    table,
+                    key,
+                    id,
+                    item,
+                    expected_version,
+                } => {
+                    let id = match id {
+                        serde_json::Value::String(s) => s,
+                        id => id.to_string(),
+                    };
+                    let Some(expected) = expected_version else {
+                        self.update_by_keys(&table, &key, vec![(id, item)]).await?;
+                        continue;
+                    };
+                    let filters = [
+                        (key.as_str(), id.clone()),
+                        (expected.column.as_str(), expected.version.to_string()),
+                    ];
+                    if self.update_where(&table, &filters, &item).await? == 0 {
+                        return Err(anyhow::Error::new(Conflict {
+                            table,
+                            id,
+                            version: expected.version,
+                        }));
+                    }
+                }
+                Operation::Delete { table, key, id } => {
+                    self.delete_by_keys(&table, &key, vec![id]).await?
+                }
+            }
+        }
+        Ok(())
+    }
+}
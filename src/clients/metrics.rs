@@ -0,0 +1,350 @@
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::time::Instant;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::error::StatusError;
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+
+/// Counter of calls, labelled with `table`, `operation` and `outcome`.
+pub const OPERATIONS_TOTAL: &str = "ext_crud_operations_total";
+/// Histogram of call latencies in seconds, labelled with `table`, `operation` and `outcome`.
+pub const OPERATION_DURATION_SECONDS: &str = "ext_crud_operation_duration_seconds";
+
+/// Registers the descriptions of the metrics emitted by `MetricsClient` with the
+/// installed recorder.
+pub fn describe_metrics() {
+    metrics::describe_counter!(OPERATIONS_TOTAL, "Number of CRUD operations.");
+    metrics::describe_histogram!(
+        OPERATION_DURATION_SECONDS,
+        metrics::Unit::Seconds,
+        "Latency of CRUD operations."
+    );
+}
+
+/// The `outcome` label of a failed call: `client_error` and `server_error` for 4xx and
+/// 5xx statuses, `transport_error` for connection failures and timeouts, `error` otherwise.
+pub fn error_class(error: &anyhow::Error) -> &'static str {
+    for cause in error.chain() {
+        if let Some(status) = cause.downcast_ref::<StatusError>() {
+            return match status.status {
+                400..=499 => "client_error",
+                500..=599 => "server_error",
+                _ => "error",
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_connect() || e.is_timeout() || e.is_request() {
+                return "transport_error";
+            }
+        }
+    }
+    "error"
+}
+
+/// Emits a counter and a latency histogram per table, operation (`create`, `read`,
+/// `update`, `delete` or `batch`) and outcome for every call to the inner client,
+/// through the `metrics` facade.
+///
+/// Batches are recorded once for each table they touch.
+pub struct MetricsClient<C: Client> {
+    inner: C,
+}
+
+impl<C: Client> MetricsClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    async fn measure<T, Fut>(&self, tables: &[&str], operation: &'static str, f: Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let started = Instant::now();
+        let result = f.await;
+        let elapsed = started.elapsed().as_secs_f64();
+        let outcome = match &result {
+            Ok(_) => "ok",
+            Err(e) => error_class(e),
+        };
+        for table in tables {
+            let labels = [
+                ("table", table.to_string()),
+                ("operation", operation.to_string()),
+                ("outcome", outcome.to_string()),
+            ];
+            metrics::counter!(OPERATIONS_TOTAL, &labels).increment(1);
+            metrics::histogram!(OPERATION_DURATION_SECONDS, &labels).record(elapsed);
+        }
+        result
+    }
+}
+
+/// Wraps clients into a `MetricsClient`.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsLayer;
+
+impl<C: Client> Layer<C> for MetricsLayer {
+    type Client = MetricsClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        MetricsClient::new(inner)
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for MetricsClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        self.measure(&[table], "create", self.inner.create(table, item))
+            .await
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.measure(&[table], "read", self.inner.find_by_keys(table, key, ids))
+            .await
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.measure(
+            &[table],
+            "read",
+            self.inner.find_by_keys_select(table, key, ids, columns),
+        )
+        .await
+    }
+
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        self.measure(&[table], "read", self.inner.find_rows(table, key, ids))
+            .await
+    }
+
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        self.measure(
+            &[table],
+            "read",
+            self.inner.find_by_keys_typed(table, key, ids),
+        )
+        .await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.measure(
+            &[table],
+            "read",
+            self.inner.find_where(table, filters, order, limit),
+        )
+        .await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.measure(
+            &[table],
+            "read",
+            self.inner.find_range(table, key, after, limit),
+        )
+        .await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.measure(
+            &[table],
+            "read",
+            self.inner.find_where_rows(table, filters, order, limit),
+        )
+        .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.measure(
+            &[table],
+            "read",
+            self.inner.find_range_rows(table, key, after, limit),
+        )
+        .await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        self.measure(
+            &[table],
+            "update",
+            self.inner.update_by_keys(table, key, items),
+        )
+        .await
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        self.measure(
+            &[table],
+            "delete",
+            self.inner.delete_by_keys(table, key, ids),
+        )
+        .await
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        self.measure(
+            &[table],
+            "update",
+            self.inner.update_where(table, filters, item),
+        )
+        .await
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        let tables = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Create { table, .. }
+                | Operation::Update { table, .. }
+                | Operation::Delete { table, .. } => table.clone(),
+            })
+            .collect::<BTreeSet<_>>();
+        let tables = tables.iter().map(String::as_str).collect::<Vec<_>>();
+        self.measure(&tables, "batch", self.inner.execute_batch(operations))
+            .await
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use serde_json::json;
+
+    #[test]
+    fn records_operations_per_table() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let client = MetricsClient::new(MemoryClient::default());
+                client.create("users", &json!({ "id": 1 })).await.unwrap();
+                client.find_by_keys("users", "id", vec![1]).await.unwrap();
+                client.find_by_keys("users", "id", vec![2]).await.unwrap();
+                client
+                    .update_by_keys("users", "id", vec![("1", "not an object")])
+                    .await
+                    .unwrap_err();
+            });
+        });
+
+        let rendered = handle.render();
+        let count = |labels: &str| {
+            rendered
+                .lines()
+                .find(|line| line.starts_with(&format!("{}{{{}}}", OPERATIONS_TOTAL, labels)))
+                .and_then(|line| line.rsplit(' ').next())
+                .map(str::to_string)
+        };
+        assert_eq!(
+            count(r#"table="users",operation="create",outcome="ok""#).as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            count(r#"table="users",operation="read",outcome="ok""#).as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            count(r#"table="users",operation="update",outcome="error""#).as_deref(),
+            Some("1")
+        );
+        assert!(rendered.contains(OPERATION_DURATION_SECONDS));
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let status = |status| {
+            anyhow::Error::new(StatusError {
+                status,
+                retry_after: None,
+            })
+        };
+        assert_eq!(error_class(&status(404).context("read")), "client_error");
+        assert_eq!(error_class(&status(503)), "server_error");
+        assert_eq!(error_class(&anyhow::anyhow!("invalid row")), "error");
+    }
+}
@@ -1 +1,16 @@
+pub mod audit;
+pub mod cache;
 pub mod client;
+pub mod clock;
+pub mod coalesce;
+pub mod error;
+pub mod events;
+pub(crate) mod ids;
+pub mod layer;
+#[cfg(test)]
+pub(crate) mod memory;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod outbox;
+pub mod retry;
+pub mod transaction;
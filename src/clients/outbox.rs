@@ -0,0 +1,635 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::error::Conflict;
+use crate::clients::events::{changed, EntityChanged, DEFAULT_KEY};
+use crate::clients::ids::{plain_id, plain_ids};
+use crate::clients::layer::Layer;
+use crate::clients::transaction::{ExpectedVersion, Operation};
+use crate::entity::column::FilterOp;
+use crate::entity::extend::ExtendedCrud;
+
+pub const DEFAULT_OUTBOX_TABLE: &str = "outbox";
+
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A row of the outbox table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    /// Stays the same across redeliveries, for sinks deduplicating events.
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub event: EntityChanged,
+    pub created_at: DateTime<Utc>,
+    pub dispatched: bool,
+    pub dispatched_at: Option<DateTime<Utc>>,
+}
+
+/// Destination of the events relayed from the outbox.
+#[async_trait]
+pub trait EventSink: Send + Sync + 'static {
+    async fn deliver(&self, event: &OutboxEvent) -> Result<()>;
+}
+
+/// Forwards to the subscribers of an `EventLayer`, failing while there are none so the
+/// events stay in the outbox.
+#[async_trait]
+impl EventSink for broadcast::Sender<EntityChanged> {
+    async fn deliver(&self, event: &OutboxEvent) -> Result<()> {
+        self.send(event.event.clone())
+            .map(|_| ())
+            .map_err(|_| anyhow!("EventSink.deliver, no subscribers"))
+    }
+}
+
+/// Inserts an `OutboxEvent` row with every write, in the same `execute_batch` call so
+/// SQL backends commit both in one transaction.
+///
+/// The inner client must support `execute_batch`. `update_where` is batched as an update
+/// of the row by key with an expected version, the only conditional update the batches
+/// support, and fails for other filters. Writes to the outbox table itself pass through.
+pub struct OutboxClient<C: Client> {
+    inner: C,
+    table: String,
+    keys: HashMap<String, String>,
+}
+
+impl<C: Client> OutboxClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            table: DEFAULT_OUTBOX_TABLE.to_string(),
+            keys: HashMap::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Reads the key of rows created in `table` from `column` instead of `DEFAULT_KEY`.
+    pub fn key(mut self, table: &str, column: &str) -> Self {
+        self.keys.insert(table.to_string(), column.to_string());
+        self
+    }
+
+    /// Reads the key of created `E` rows from its primary key.
+    pub fn entity<C2: Client, E: ExtendedCrud<C2>>(self) -> Self {
+        self.key(E::TABLE_NAME, E::PRIMARY_KEY_NAME)
+    }
+
+    fn outbox_row(&self, event: EntityChanged) -> Result<Operation> {
+        let row = OutboxEvent {
+            id: Uuid::new_v4(),
+            event,
+            created_at: self.inner.clock().now(),
+            dispatched: false,
+            dispatched_at: None,
+        };
+        Ok(Operation::Create {
+            table: self.table.clone(),
+            item: serde_json::to_value(row)?,
+        })
+    }
+
+    /// Applies `operations` with an outbox row after each of them.
+    async fn write(&self, operations: Vec<Operation>) -> Result<()> {
+        let mut batch = Vec::with_capacity(operations.len() * 2);
+        for operation in operations {
+            let outboxed = match &operation {
+                Operation::Create { table, .. }
+                | Operation::Update { table, .. }
+                | Operation::Delete { table, .. } => *table != self.table,
+            };
+            let event = outboxed.then(|| changed(operation.clone(), &self.keys));
+            batch.push(operation);
+            if let Some(event) = event {
+                batch.push(self.outbox_row(event)?);
+            }
+        }
+        self.inner
+            .execute_batch(batch)
+            .await
+            .context("OutboxClient.write failed")
+    }
+}
+
+/// Wraps clients into an `OutboxClient`.
+#[derive(Debug, Clone)]
+pub struct OutboxLayer {
+    table: String,
+    keys: HashMap<String, String>,
+}
+
+impl Default for OutboxLayer {
+    fn default() -> Self {
+        Self {
+            table: DEFAULT_OUTBOX_TABLE.to_string(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl OutboxLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Reads the key of rows created in `table` from `column` instead of `DEFAULT_KEY`.
+    pub fn key(mut self, table: &str, column: &str) -> Self {
+        self.keys.insert(table.to_string(), column.to_string());
+        self
+    }
+
+    /// Reads the key of created `E` rows from its primary key.
+    pub fn entity<C: Client, E: ExtendedCrud<C>>(self) -> Self {
+        self.key(E::TABLE_NAME, E::PRIMARY_KEY_NAME)
+    }
+}
+
+impl<C: Client> Layer<C> for OutboxLayer {
+    type Client = OutboxClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        OutboxClient {
+            inner,
+            table: self.table.clone(),
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for OutboxClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        if table == self.table {
+            return self.inner.create(table, item).await;
+        }
+        self.write(vec![Operation::Create {
+            table: table.to_string(),
+            item: serde_json::to_value(item)?,
+        }])
+        .await
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_by_keys(table, key, ids).await
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner
+            .find_by_keys_select(table, key, ids, columns)
+            .await
+    }
+
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_rows(table, key, ids).await
+    }
+
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        self.inner.find_by_keys_typed(table, key, ids).await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.inner.find_range(table, key, after, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner
+            .find_where_rows(table, filters, order, limit)
+            .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.inner.find_range_rows(table, key, after, limit).await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        if table == self.table {
+            return self.inner.update_by_keys(table, key, items).await;
+        }
+        let operations = items
+            .into_iter()
+            .map(|(id, item)| {
+                Ok(Operation::Update {
+                    table: table.to_string(),
+                    key: key.to_string(),
+                    id: plain_id(id.as_ref()),
+                    item: serde_json::to_value(item)?,
+                    expected_version: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.write(operations).await
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        if table == self.table {
+            return self.inner.delete_by_keys(table, key, ids).await;
+        }
+        let operations = plain_ids(&ids)?
+            .into_iter()
+            .map(|id| Operation::Delete {
+                table: table.to_string(),
+                key: key.to_string(),
+                id,
+            })
+            .collect();
+        self.write(operations).await
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        if table == self.table {
+            return self.inner.update_where(table, filters, item).await;
+        }
+        let tag = "OutboxClient.update_where failed";
+        let operation = versioned_update(table, filters, item, &self.keys).context(tag)?;
+        match self.write(vec![operation]).await {
+            Ok(()) => Ok(1),
+            Err(e) if e.downcast_ref::<Conflict>().is_some() => Ok(0),
+            Err(e) => Err(e.context(tag)),
+        }
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        self.write(operations).await
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+/// The batched form of an `update_where` by key and version, the key being the filter on
+/// the registered key column of `table`, else the first one as `ExtendedCrud` passes it.
+fn versioned_update<T: Serialize>(
+    table: &str,
+    filters: &[(&str, String)],
+    item: &T,
+    keys: &HashMap<String, String>,
+) -> Result<Operation> {
+    let [first, second] = filters else {
+        bail!(
+            "only updates by key and version can be batched, got {} filters",
+            filters.len()
+        );
+    };
+    let column = keys.get(table).map_or(DEFAULT_KEY, String::as_str);
+    let ((key, id), (version_column, version)) = if second.0 == column {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    let version = version.parse::<i64>().map_err(|_| {
+        anyhow!(
+            "version {} of {} is not an integer",
+            version,
+            version_column
+        )
+    })?;
+    Ok(Operation::Update {
+        table: table.to_string(),
+        key: key.to_string(),
+        id: plain_id(id),
+        item: serde_json::to_value(item)?,
+        expected_version: Some(ExpectedVersion {
+            column: version_column.to_string(),
+            version,
+        }),
+    })
+}
+
+/// Delivers the undispatched rows of the outbox to a sink, oldest first, and marks them
+/// as dispatched. Each poll reads one batch with `Client::find_where`, which the client
+/// must support.
+///
+/// Delivery is at least once: a row delivered but not yet marked when the process stops
+/// is delivered again by the next relay.
+pub struct OutboxRelay<C: Client, S: EventSink> {
+    client: C,
+    sink: S,
+    table: String,
+    batch_size: usize,
+    interval: Duration,
+}
+
+impl<C: Client, S: EventSink> OutboxRelay<C, S> {
+    pub fn new(client: C, sink: S) -> Self {
+        Self {
+            client,
+            sink,
+            table: DEFAULT_OUTBOX_TABLE.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Rows delivered per poll at most.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Wait between polls finding nothing to deliver, or failing.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Delivers one batch and returns the number of rows dispatched. Stops at the first
+    /// failed delivery, leaving that row and the following ones for the next poll.
+    pub async fn run_once(&self) -> Result<usize> {
+        let tag = "OutboxRelay.run_once failed";
+        let rows = self
+            .client
+            .find_where(
+                &self.table,
+                &[("dispatched", FilterOp::Eq, false.into())],
+                &[("created_at", true)],
+                Some(self.batch_size),
+            )
+            .await
+            .context(tag)?;
+        let events = rows
+            .into_iter()
+            .map(serde_json::from_value::<OutboxEvent>)
+            .collect::<Result<Vec<_>, _>>()
+            .context(tag)?;
+
+        for (dispatched, event) in events.iter().enumerate() {
+            if let Err(e) = self.sink.deliver(event).await {
+                return if dispatched == 0 {
+                    Err(e.context(tag))
+                } else {
+                    warn!(
+                        "OutboxRelay.run_once, delivery of {} failed: {:#}",
+                        event.id, e
+                    );
+                    Ok(dispatched)
+                };
+            }
+            self.client
+                .update_by_keys(
+                    &self.table,
+                    "id",
+                    vec![(
+                        event.id.to_string(),
+                        serde_json::json!({
+                            "dispatched": true,
+                            "dispatched_at": self.client.clock().now(),
+                        }),
+                    )],
+                )
+                .await
+                .context(tag)?;
+        }
+        Ok(events.len())
+    }
+
+    /// Polls until the task is aborted, draining full batches without waiting.
+    pub async fn run(self) {
+        loop {
+            match self.run_once().await {
+                Ok(dispatched) if dispatched == self.batch_size => continue,
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "OutboxRelay.run failed, retrying in {:?}: {:#}",
+                    self.interval, e
+                ),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::events::ChangeOp;
+    use crate::clients::memory::MemoryClient;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<OutboxEvent>>,
+        fail_on: Option<serde_json::Value>,
+    }
+
+    #[async_trait]
+    impl EventSink for Arc<RecordingSink> {
+        async fn deliver(&self, event: &OutboxEvent) -> Result<()> {
+            if self.fail_on.as_ref() == Some(&event.event.key) {
+                return Err(anyhow!("sink unavailable"));
+            }
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_events_in_the_same_batch() {
+        let client = OutboxClient::new(MemoryClient::default());
+        client
+            .create("users", &json!({ "id": 1, "name": "john" }))
+            .await
+            .unwrap();
+        client
+            .update_by_keys("users", "id", vec![("1", json!({ "name": "jane" }))])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            client.inner().calls(),
+            [
+                "execute_batch 2",
+                "create users",
+                "create outbox",
+                "execute_batch 2",
+                "update_by_keys users 1",
+                "create outbox",
+            ]
+        );
+        let rows = client.inner().rows(DEFAULT_OUTBOX_TABLE);
+        let event = serde_json::from_value::<OutboxEvent>(rows[1].clone()).unwrap();
+        assert_eq!(
+            (event.event.op, event.event.key, event.dispatched),
+            (ChangeOp::Update, json!("1"), false)
+        );
+    }
+
+    #[tokio::test]
+    async fn batches_versioned_updates_with_their_events() {
+        let client = OutboxClient::new(MemoryClient::default());
+        client
+            .create("users", &json!({ "id": 1, "version": 0 }))
+            .await
+            .unwrap();
+        let filters = [("id", "1".to_string()), ("version", "0".to_string())];
+        let item = json!({ "name": "jane", "version": 1 });
+        assert_eq!(
+            client.update_where("users", &filters, &item).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            client.update_where("users", &filters, &item).await.unwrap(),
+            0
+        );
+        assert!(client
+            .update_where("users", &filters[..1], &item)
+            .await
+            .is_err());
+
+        assert_eq!(
+            client.inner().calls()[3..],
+            [
+                "execute_batch 2",
+                "update_where users 2",
+                "create outbox",
+                "execute_batch 2",
+                "update_where users 2",
+            ]
+        );
+        let rows = client.inner().rows(DEFAULT_OUTBOX_TABLE);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["key"], json!("1"));
+    }
+
+    #[tokio::test]
+    async fn relays_undispatched_events_in_order() {
+        let client = OutboxClient::new(MemoryClient::default());
+        for id in 1..=3 {
+            client.create("users", &json!({ "id": id })).await.unwrap();
+        }
+        let sink = Arc::new(RecordingSink {
+            fail_on: Some(json!(3)),
+            ..Default::default()
+        });
+        let relay = OutboxRelay::new(client, sink.clone()).batch_size(10);
+
+        assert_eq!(relay.run_once().await.unwrap(), 2);
+        assert!(relay.run_once().await.is_err());
+        let keys = sink
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.event.key.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, [json!(1), json!(2)]);
+        let dispatched = relay
+            .client
+            .inner()
+            .rows(DEFAULT_OUTBOX_TABLE)
+            .into_iter()
+            .map(|row| row["dispatched"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(dispatched, [json!(true), json!(true), json!(false)]);
+        assert!(relay
+            .client
+            .inner()
+            .calls()
+            .contains(&"find_where outbox 1".to_string()));
+    }
+}
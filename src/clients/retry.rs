@@ -0,0 +1,429 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+use crate::clients::client::Client;
+use crate::clients::clock::Clock;
+use crate::clients::error::{AuditFailed, StatusError};
+use crate::clients::layer::Layer;
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Also retries `create`, `update_where` and `execute_batch`, which may be applied
+    /// twice when only the response was lost.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with equal jitter: half of the delay is fixed, half is random.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+/// Whether `error` comes from a connection failure, a timeout or a transient status,
+/// unless the write itself succeeded and only its auditing failed.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    if error.downcast_ref::<AuditFailed>().is_some() {
+        return false;
+    }
+    error.chain().any(|cause| {
+        if let Some(status) = cause.downcast_ref::<StatusError>() {
+            status.is_transient()
+        } else if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            e.is_connect() || e.is_timeout()
+        } else {
+            false
+        }
+    })
+}
+
+fn retry_after(error: &anyhow::Error) -> Option<Duration> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<StatusError>())
+        .and_then(|status| status.retry_after)
+}
+
+/// Retries transient failures of the inner client with exponential backoff, honoring
+/// `Retry-After`.
+///
+/// Reads, deletes and updates by key are idempotent and retried by default, creates,
+/// conditional updates and batches only with `RetryPolicy::retry_non_idempotent`.
+pub struct RetryingClient<C: Client> {
+    inner: C,
+    policy: RetryPolicy,
+}
+
+impl<C: Client> RetryingClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self::with_policy(inner, RetryPolicy::default())
+    }
+
+    pub fn with_policy(inner: C, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    async fn retry<T, F, Fut>(&self, operation: &str, idempotent: bool, mut f: F) -> Result<T>
+    where
+        T: Send,
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T>> + Send,
+    {
+        let retryable = idempotent || self.policy.retry_non_idempotent;
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(e) if retryable && attempt < self.policy.max_retries && is_transient(&e) => {
+                    let delay = retry_after(&e).unwrap_or_else(|| self.policy.backoff(attempt));
+                    warn!(
+                        "RetryingClient.{} failed, retrying in {:?}: {:#}",
+                        operation, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Wraps clients into a `RetryingClient` with `policy`.
+#[derive(Debug, Clone, Default)]
+pub struct RetryLayer {
+    pub policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<C: Client> Layer<C> for RetryLayer {
+    type Client = RetryingClient<C>;
+
+    fn layer(&self, inner: C) -> Self::Client {
+        RetryingClient::with_policy(inner, self.policy.clone())
+    }
+}
+
+fn to_values<K: Serialize>(ids: Vec<K>) -> Result<Vec<serde_json::Value>> {
+    ids.into_iter()
+        .map(|id| serde_json::to_value(id).map_err(|e| anyhow!(e)))
+        .collect()
+}
+
+#[async_trait]
+impl<C: Client> Client for RetryingClient<C> {
+    type Row = C::Row;
+
+    async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
+        self.retry("create", false, || self.inner.create(table, item))
+            .await
+    }
+
+    async fn find_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<serde_json::Value>> {
+        let ids = to_values(ids)?;
+        self.retry("find_by_keys", true, || {
+            self.inner.find_by_keys(table, key, ids.clone())
+        })
+        .await
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        let ids = to_values(ids)?;
+        self.retry("find_by_keys_select", true, || {
+            self.inner
+                .find_by_keys_select(table, key, ids.clone(), columns)
+        })
+        .await
+    }
+
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        let ids = to_values(ids)?;
+        self.retry("find_rows", true, || {
+            self.inner.find_rows(table, key, ids.clone())
+        })
+        .await
+    }
+
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        let ids = to_values(ids)?;
+        self.retry("find_by_keys_typed", true, || {
+            self.inner.find_by_keys_typed(table, key, ids.clone())
+        })
+        .await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.retry("find_where", true, || {
+            self.inner.find_where(table, filters, order, limit)
+        })
+        .await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.retry("find_range", true, || {
+            self.inner.find_range(table, key, after, limit)
+        })
+        .await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.retry("find_where_rows", true, || {
+            self.inner.find_where_rows(table, filters, order, limit)
+        })
+        .await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.retry("find_range_rows", true, || {
+            self.inner.find_range_rows(table, key, after, limit)
+        })
+        .await
+    }
+
+    async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        items: Vec<(K, T)>,
+    ) -> Result<()>
+    where
+        K: ToString + AsRef<str>,
+    {
+        let items = items
+            .into_iter()
+            .map(|(id, item)| Ok((id.as_ref().to_string(), serde_json::to_value(item)?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.retry("update_by_keys", true, || {
+            self.inner.update_by_keys(table, key, items.clone())
+        })
+        .await
+    }
+
+    async fn delete_by_keys<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<()> {
+        let ids = to_values(ids)?;
+        self.retry("delete_by_keys", true, || {
+            self.inner.delete_by_keys(table, key, ids.clone())
+        })
+        .await
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        self.retry("update_where", false, || {
+            self.inner.update_where(table, filters, item)
+        })
+        .await
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        self.retry("execute_batch", false, || {
+            self.inner.execute_batch(operations.clone())
+        })
+        .await
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.inner.clock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct FlakyClient {
+        failures: AtomicU32,
+        status: u16,
+        calls: AtomicU32,
+    }
+
+    impl FlakyClient {
+        fn new(failures: u32, status: u16) -> Self {
+            Self {
+                failures: AtomicU32::new(failures),
+                status,
+                calls: AtomicU32::new(0),
+            }
+        }
+
+        fn call(&self) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                let status = StatusError {
+                    status: self.status,
+                    retry_after: None,
+                };
+                return Err(anyhow::Error::new(status).context("FlakyClient"));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Client for FlakyClient {
+        type Row = serde_json::Value;
+
+        async fn create<T: Serialize + Send + Sync>(&self, _: &str, _: &T) -> Result<()> {
+            self.call()
+        }
+
+        async fn find_by_keys<K: Serialize + Send + Sync>(
+            &self,
+            _: &str,
+            _: &str,
+            ids: Vec<K>,
+        ) -> Result<Vec<serde_json::Value>> {
+            self.call()?;
+            to_values(ids)
+        }
+
+        async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
+            &self,
+            _: &str,
+            _: &str,
+            _: Vec<(K, T)>,
+        ) -> Result<()>
+        where
+            K: ToString + AsRef<str>,
+        {
+            self.call()
+        }
+
+        async fn delete_by_keys<K: Serialize + Send + Sync>(
+            &self,
+            _: &str,
+            _: &str,
+            _: Vec<K>,
+        ) -> Result<()> {
+            bail!("not used")
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_transient_reads() {
+        let client = RetryingClient::with_policy(FlakyClient::new(2, 503), policy());
+        let founds = client
+            .find_by_keys("users", "id", vec![1, 2])
+            .await
+            .unwrap();
+        assert_eq!(founds, vec![serde_json::json!(1), serde_json::json!(2)]);
+        assert_eq!(client.inner().calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let client = RetryingClient::with_policy(FlakyClient::new(10, 429), policy());
+        let result = client.update_by_keys("users", "id", vec![("1", 1)]).await;
+        assert!(result.is_err());
+        assert_eq!(client.inner().calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_creates_or_permanent_errors() {
+        let client = RetryingClient::with_policy(FlakyClient::new(1, 503), policy());
+        assert!(client.create("users", &1).await.is_err());
+        assert_eq!(client.inner().calls.load(Ordering::SeqCst), 1);
+
+        let client = RetryingClient::with_policy(FlakyClient::new(1, 400), policy());
+        assert!(client.find_by_keys("users", "id", vec![1]).await.is_err());
+        assert_eq!(client.inner().calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::clients::client::Client;
+use crate::entity::extend::{create_payload, soft_delete_payload, update_payload, ExtendedCrud};
+
+/// A single write queued in a `Transaction`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    Create {
+        table: String,
+        item: serde_json::Value,
+    },
+    Update {
+        table: String,
+        key: String,
+        id: serde_json::Value,
+        item: serde_json::Value,
+        /// When set, the batch fails with `Conflict` unless the row is still at this version.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_version: Option<ExpectedVersion>,
+    },
+    Delete {
+        table: String,
+        key: String,
+        id: serde_json::Value,
+    },
+}
+
+/// The version column value an `Operation::Update` requires the row to have.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedVersion {
+    pub column: String,
+    pub version: i64,
+}
+
+/// A unit of work collecting writes across entities, applied atomically by
+/// `Client::execute_batch` on `commit`.
+pub struct Transaction<'a, C: Client> {
+    client: &'a C,
+    operations: Vec<Operation>,
+}
+
+impl<'a, C: Client> Transaction<'a, C> {
+    pub fn new(client: &'a C) -> Self {
+        Self {
+            client,
+            operations: vec![],
+        }
+    }
+
+    /// Queues the payload of `entity` as it is; the lifecycle hooks of `ExtendedCrud` are
+    /// async and are not run for queued writes.
+    pub fn create<E: ExtendedCrud<C>>(&mut self, entity: &E) -> Result<()> {
+        let tag = "Transaction.create failed";
+        entity
+            .validate()
+            .map_err(|e| anyhow::Error::new(e).context(tag))?;
+        self.operations.push(Operation::Create {
+            table: E::TABLE_NAME.to_string(),
+            item: create_payload(self.client, entity).map_err(|e| anyhow!(e).context(tag))?,
+        });
+        Ok(())
+    }
+
+    /// Like `create`, queues `entity` without running its hooks. Versioned entities are
+    /// only written while the row is still at `version()`, and move to `version() + 1`.
+    pub fn update<E: ExtendedCrud<C>>(&mut self, entity: &E) -> Result<()> {
+        let tag = "Transaction.update failed";
+        entity
+            .validate()
+            .map_err(|e| anyhow::Error::new(e).context(tag))?;
+        let mut item = update_payload(self.client, entity).map_err(|e| anyhow!(e).context(tag))?;
+        let expected_version = match (E::VERSION_NAME, entity.version()) {
+            (Some(column), Some(version)) => {
+                if let Some(row) = item.as_object_mut() {
+                    row.insert(column.to_string(), (version + 1).into());
+                }
+                Some(ExpectedVersion {
+                    column: column.to_string(),
+                    version,
+                })
+            }
+            _ => None,
+        };
+        self.operations.push(Operation::Update {
+            table: E::TABLE_NAME.to_string(),
+            key: E::PRIMARY_KEY_NAME.to_string(),
+            id: serde_json::to_value(entity.primary_key()).map_err(|e| anyhow!(e).context(tag))?,
+            item,
+            expected_version,
+        });
+        Ok(())
+    }
+
+    pub fn delete<E: ExtendedCrud<C>>(&mut self, id: &E::PrimaryKey) -> Result<()> {
+        let tag = "Transaction.delete failed";
+        if E::SOFT_DELETE_NAME.is_some() {
+            self.operations.push(Operation::Update {
+                table: E::TABLE_NAME.to_string(),
+                key: E::PRIMARY_KEY_NAME.to_string(),
+                id: serde_json::to_value(id).map_err(|e| anyhow!(e).context(tag))?,
+                item: soft_delete_payload::<C, E>(self.client, true)
+                    .map_err(|e| anyhow!(e).context(tag))?,
+                expected_version: None,
+            });
+            return Ok(());
+        }
+        self.operations.push(Operation::Delete {
+            table: E::TABLE_NAME.to_string(),
+            key: E::PRIMARY_KEY_NAME.to_string(),
+            id: serde_json::to_value(id).map_err(|e| anyhow!(e).context(tag))?,
+        });
+        Ok(())
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub async fn commit(self) -> Result<()> {
+        if self.operations.is_empty() {
+            return Ok(());
+        }
+        self.client
+            .execute_batch(self.operations)
+            .await
+            .context("Transaction.commit failed")
+    }
+
+    /// Discards every queued operation; nothing has been sent to the backend yet.
+    pub fn rollback(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::error::Conflict;
+    use crate::clients::memory::MemoryClient;
+    use crate::entity::column::Column;
+    use crate::entity::extend::TryFromError;
+    use crate::entity::row::EntityRow;
+    use crate::entity::validate::{Validate, ValidationErrors};
+    use ext_crud_derive::ExtendedCrud;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("documents")]
+    struct Document {
+        id: String,
+        body: String,
+        #[version]
+        version: i64,
+    }
+
+    #[tokio::test]
+    async fn checks_versions_of_batched_updates() {
+        let client = MemoryClient::default();
+        let mut document = Document {
+            id: "doc".to_string(),
+            body: "draft".to_string(),
+            version: 0,
+        };
+        document.clone().create(&client).await.unwrap();
+
+        document.body = "first".to_string();
+        let mut tx = Transaction::new(&client);
+        tx.update(&document).unwrap();
+        tx.commit().await.unwrap();
+        let stored = Document::read(&client, "doc".to_string()).await.unwrap();
+        assert_eq!((stored.body.as_str(), stored.version), ("first", 1));
+
+        document.body = "lost".to_string();
+        let mut tx = Transaction::new(&client);
+        tx.update(&document).unwrap();
+        let error = tx.commit().await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Conflict>(),
+            Some(&Conflict {
+                table: "documents".to_string(),
+                id: "doc".to_string(),
+                version: 0,
+            })
+        );
+        let stored = Document::read(&client, "doc".to_string()).await.unwrap();
+        assert_eq!(stored.body, "first");
+    }
+}
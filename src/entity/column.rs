@@ -0,0 +1,177 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+/// Comparison of a column with a value in `Client::find_where` filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// The value is an array of the accepted values.
+    In,
+    /// The value is ignored.
+    IsNull,
+    /// The value is ignored.
+    NotNull,
+}
+
+/// A column of the entity `E` holding values of type `T`, generated as an associated
+/// constant by the `ExtendedCrud` derive, e.g. `User::AGE`.
+pub struct Column<E, T> {
+    name: &'static str,
+    marker: PhantomData<fn() -> (E, T)>,
+}
+
+impl<E, T> Column<E, T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            marker: PhantomData,
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn asc(&self) -> Order<E> {
+        Order::new(self.name, true)
+    }
+
+    pub fn desc(&self) -> Order<E> {
+        Order::new(self.name, false)
+    }
+
+    pub fn is_null(&self) -> Filter<E> {
+        Filter::new(self.name, FilterOp::IsNull, serde_json::Value::Null)
+    }
+
+    pub fn is_not_null(&self) -> Filter<E> {
+        Filter::new(self.name, FilterOp::NotNull, serde_json::Value::Null)
+    }
+}
+
+impl<E, T: Serialize> Column<E, T> {
+    fn compare(&self, op: FilterOp, value: impl Into<T>) -> Filter<E> {
+        Filter::new(self.name, op, serde_json::json!(value.into()))
+    }
+
+    pub fn eq(&self, value: impl Into<T>) -> Filter<E> {
+        self.compare(FilterOp::Eq, value)
+    }
+
+    pub fn neq(&self, value: impl Into<T>) -> Filter<E> {
+        self.compare(FilterOp::Neq, value)
+    }
+
+    pub fn gt(&self, value: impl Into<T>) -> Filter<E> {
+        self.compare(FilterOp::Gt, value)
+    }
+
+    pub fn gte(&self, value: impl Into<T>) -> Filter<E> {
+        self.compare(FilterOp::Gte, value)
+    }
+
+    pub fn lt(&self, value: impl Into<T>) -> Filter<E> {
+        self.compare(FilterOp::Lt, value)
+    }
+
+    pub fn lte(&self, value: impl Into<T>) -> Filter<E> {
+        self.compare(FilterOp::Lte, value)
+    }
+
+    pub fn is_in<V: Into<T>>(&self, values: impl IntoIterator<Item = V>) -> Filter<E> {
+        let values = values
+            .into_iter()
+            .map(|value| serde_json::json!(value.into()))
+            .collect();
+        Filter::new(self.name, FilterOp::In, serde_json::Value::Array(values))
+    }
+}
+
+impl<E, T> Clone for Column<E, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E, T> Copy for Column<E, T> {}
+
+impl<E, T> fmt::Debug for Column<E, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Column").field(&self.name).finish()
+    }
+}
+
+/// A condition on a column of `E`, built from its `Column` constants.
+pub struct Filter<E> {
+    pub column: &'static str,
+    pub op: FilterOp,
+    pub value: serde_json::Value,
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<E> Filter<E> {
+    pub fn new(column: &'static str, op: FilterOp, value: serde_json::Value) -> Self {
+        Self {
+            column,
+            op,
+            value,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for Filter<E> {
+    fn clone(&self) -> Self {
+        Self::new(self.column, self.op, self.value.clone())
+    }
+}
+
+impl<E> fmt::Debug for Filter<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("column", &self.column)
+            .field("op", &self.op)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// A sort key on a column of `E`.
+pub struct Order<E> {
+    pub column: &'static str,
+    pub ascending: bool,
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<E> Order<E> {
+    pub fn new(column: &'static str, ascending: bool) -> Self {
+        Self {
+            column,
+            ascending,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for Order<E> {
+    fn clone(&self) -> Self {
+        Self::new(self.column, self.ascending)
+    }
+}
+
+impl<E> fmt::Debug for Order<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Order")
+            .field("column", &self.column)
+            .field("ascending", &self.ascending)
+            .finish()
+    }
+}
@@ -1,8 +1,14 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::clients::client::Client;
+use crate::clients::error::Conflict;
+use crate::entity::query::Query;
+use crate::entity::row::EntityRow;
+use crate::entity::validate::{Validate, ValidationErrors};
+use crate::telemetry::{crud_span, instrumented, trace_payload};
 
 #[async_trait]
 pub trait ExtendedCrud<C: Client>:
@@ -12,7 +18,8 @@ pub trait ExtendedCrud<C: Client>:
     + Send
     + Sync
     + 'static
-    + TryFromError<serde_json::Value, serde_json::Error>
+    + TryFromError<C::Row, <C::Row as EntityRow>::Error>
+    + Validate
 {
     type PrimaryKey: Serialize + DeserializeOwned + Send + Sync + 'static + ToString;
 
@@ -20,83 +27,553 @@ pub trait ExtendedCrud<C: Client>:
 
     const PRIMARY_KEY_NAME: &'static str;
 
-    async fn create(self, client: &C) -> Result<()> {
-        client
-            .create(Self::TABLE_NAME, &self)
-            .await
-            .map_err(|e| anyhow!(e).context("ExtendedCrud.create failed"))
+    /// Columns whose values are replaced by `REDACTED` in logged payloads.
+    const SENSITIVE_FIELDS: &'static [&'static str] = &[];
+
+    /// Version column for optimistic concurrency control. When set, `update` and
+    /// `update_many` only write rows still at `version()` and store `version() + 1`,
+    /// failing with `Conflict` otherwise, as do the updates of a `Transaction`.
+    const VERSION_NAME: Option<&'static str> = None;
+
+    /// Column set to the client clock's time by `create` and never written by `update`.
+    const CREATED_AT_NAME: Option<&'static str> = None;
+
+    /// Column set to the client clock's time by `create` and `update`.
+    const UPDATED_AT_NAME: Option<&'static str> = None;
+
+    /// Nullable timestamp column marking deleted rows. When set, `delete` and
+    /// `delete_many` fill it instead of removing rows, reads skip rows where it is set,
+    /// `update` never writes it, so only `restore` clears it, and `purge` removes rows for
+    /// good. Reads tell deleted rows by `is_deleted`.
+    const SOFT_DELETE_NAME: Option<&'static str> = None;
+
+    async fn create(mut self, client: &C) -> Result<()> {
+        let span = crud_span!("ExtendedCrud.create", Self::TABLE_NAME, "create", 1);
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.create failed";
+            self.before_create(client).await.context(tag)?;
+            self.validate()
+                .map_err(|e| anyhow::Error::new(e).context(tag))?;
+            let item = create_payload(client, &self).map_err(|e| anyhow!(e).context(tag))?;
+            trace_payload(&item, Self::SENSITIVE_FIELDS);
+            client
+                .create(Self::TABLE_NAME, &item)
+                .await
+                .map_err(|e| anyhow!(e).context(tag))?;
+            self.after_create(client).await.context(tag)
+        })
+        .await
     }
 
     async fn read(client: &C, id: Self::PrimaryKey) -> Result<Self> {
-        let tag = "ExtendedCrud.read failed";
-        let mut founds = client
-            .find_by_keys::<Self::PrimaryKey>(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, vec![id])
+        let span = crud_span!("ExtendedCrud.read", Self::TABLE_NAME, "read", 1);
+        instrumented(
+            span,
+            read_one(client, id, false, "ExtendedCrud.read failed"),
+        )
+        .await
+    }
+
+    /// Like `read`, but also finds soft-deleted rows.
+    async fn read_with_deleted(client: &C, id: Self::PrimaryKey) -> Result<Self> {
+        let span = crud_span!(
+            "ExtendedCrud.read_with_deleted",
+            Self::TABLE_NAME,
+            "read",
+            1
+        );
+        instrumented(
+            span,
+            read_one(client, id, true, "ExtendedCrud.read_with_deleted failed"),
+        )
+        .await
+    }
+
+    async fn read_many(ids: Vec<Self::PrimaryKey>, client: &C) -> Result<Vec<Self>> {
+        let span = crud_span!(
+            "ExtendedCrud.read_many",
+            Self::TABLE_NAME,
+            "read",
+            ids.len()
+        );
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.read_many failed";
+            let mut entities = find::<C, Self>(client, ids, false, tag).await?;
+            for entity in &mut entities {
+                entity.after_load(client).await.context(tag)?;
+            }
+            Ok(entities)
+        })
+        .await
+    }
+
+    /// Pages through every row of the table by primary key with `Client::find_range`,
+    /// holding one page of `page_size` rows at a time. Each page starts after the last key
+    /// of the previous one, so rows are never repeated, and rows inserted meanwhile are
+    /// only returned when their key is past the current page.
+    fn stream(client: &C, page_size: usize) -> BoxStream<'_, Result<Self>> {
+        pages::<C, Self>(client, page_size).boxed()
+    }
+
+    /// Starts a filtered read of the table, skipping soft-deleted rows unless
+    /// `Query::with_deleted` is set.
+    fn query() -> Query<C, Self> {
+        Query::new()
+    }
+
+    /// Runs `before_update` and `after_update` on `self`, so changes made by the hooks
+    /// stay on it.
+    async fn update(&mut self, client: &C) -> Result<()> {
+        let span = crud_span!("ExtendedCrud.update", Self::TABLE_NAME, "update", 1);
+        instrumented(span, update_one(client, self))
             .await
-            .context(tag)?;
-        if founds.len() > 1 {
-            anyhow::bail!(format!("{}, Found more than one", tag));
+            .context("ExtendedCrud.update failed")
+    }
+
+    async fn update_many(mut items: Vec<Self>, client: &C) -> Result<()> {
+        let span = crud_span!(
+            "ExtendedCrud.update_many",
+            Self::TABLE_NAME,
+            "update",
+            items.len()
+        );
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.update_many failed";
+            for item in &mut items {
+                item.before_update(client).await.context(tag)?;
+                item.validate()
+                    .map_err(|e| anyhow::Error::new(e).context(tag))?;
+            }
+            let payloads = items
+                .iter()
+                .map(|e| update_payload(client, e).map_err(|e| anyhow!(e).context(tag)))
+                .collect::<Result<Vec<_>>>()?;
+            trace_payload(&payloads, Self::SENSITIVE_FIELDS);
+            if let Some(column) = Self::VERSION_NAME {
+                for (entity, item) in items.iter().zip(payloads) {
+                    let version = entity
+                        .version()
+                        .ok_or_else(|| anyhow!("Missing version").context(tag))?;
+                    update_if_version(client, entity, item, column, version)
+                        .await
+                        .context(tag)?;
+                }
+            } else {
+                let changes = items
+                    .iter()
+                    .zip(payloads)
+                    .map(|(e, item)| (client.as_str(e.primary_key()), item))
+                    .collect();
+                client
+                    .update_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, changes)
+                    .await
+                    .context(tag)?;
+            }
+            for item in &items {
+                item.after_update(client).await.context(tag)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Like `update`, but also moves `self` to the stored version, so that it can be
+    /// updated again without being read back.
+    async fn update_versioned(&mut self, client: &C) -> Result<()> {
+        let span = crud_span!(
+            "ExtendedCrud.update_versioned",
+            Self::TABLE_NAME,
+            "update",
+            1
+        );
+        instrumented(span, update_one(client, self))
+            .await
+            .context("ExtendedCrud.update_versioned failed")?;
+        if let Some(version) = self.version() {
+            self.set_version(version + 1);
         }
-        let value = founds
-            .pop()
-            .ok_or_else(|| anyhow!("Not found").context(tag))?;
-        Self::try_from_err(value).map_err(|e| anyhow!(e).context(tag))
+        Ok(())
     }
 
-    async fn read_many(ids: Vec<Self::PrimaryKey>, client: &C) -> Result<Vec<Self>> {
-        let tag = "ExtendedCrud.read_many failed";
-        let founds = client
-            .find_by_keys::<Self::PrimaryKey>(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, ids)
+    async fn delete(self, client: &C) -> Result<()> {
+        let span = crud_span!("ExtendedCrud.delete", Self::TABLE_NAME, "delete", 1);
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.delete failed";
+            self.before_delete(client).await.context(tag)?;
+            if Self::SOFT_DELETE_NAME.is_some() {
+                let item = soft_delete_payload::<C, Self>(client, true)
+                    .map_err(|e| anyhow!(e).context(tag))?;
+                client
+                    .update_by_keys(
+                        Self::TABLE_NAME,
+                        Self::PRIMARY_KEY_NAME,
+                        vec![(self.primary_key().to_string(), item)],
+                    )
+                    .await
+                    .context(tag)?;
+            } else {
+                let id = client.as_str(self.primary_key());
+                client
+                    .delete_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, vec![id])
+                    .await
+                    .context(tag)?;
+            }
+            self.after_delete(client).await.context(tag)
+        })
+        .await
+    }
+
+    /// Only has ids, so the delete hooks are not run.
+    async fn delete_many(ids: Vec<Self::PrimaryKey>, client: &C) -> Result<()> {
+        let span = crud_span!(
+            "ExtendedCrud.delete_many",
+            Self::TABLE_NAME,
+            "delete",
+            ids.len()
+        );
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.delete_many failed";
+            if Self::SOFT_DELETE_NAME.is_some() {
+                let item = soft_delete_payload::<C, Self>(client, true)
+                    .map_err(|e| anyhow!(e).context(tag))?;
+                let items = ids
+                    .into_iter()
+                    .map(|id| (id.to_string(), item.clone()))
+                    .collect();
+                return client
+                    .update_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, items)
+                    .await
+                    .context(tag);
+            }
+            let ids = ids.into_iter().map(|e| client.as_str(e)).collect();
+            client
+                .delete_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, ids)
+                .await
+                .context(tag)
+        })
+        .await
+    }
+
+    /// Clears the soft delete column of the row `id`, making it visible to reads again.
+    async fn restore(client: &C, id: Self::PrimaryKey) -> Result<()> {
+        let span = crud_span!("ExtendedCrud.restore", Self::TABLE_NAME, "update", 1);
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.restore failed";
+            if Self::SOFT_DELETE_NAME.is_none() {
+                anyhow::bail!("{}, {} has no soft delete column", tag, Self::TABLE_NAME);
+            }
+            let item = soft_delete_payload::<C, Self>(client, false)
+                .map_err(|e| anyhow!(e).context(tag))?;
+            client
+                .update_by_keys(
+                    Self::TABLE_NAME,
+                    Self::PRIMARY_KEY_NAME,
+                    vec![(id.to_string(), item)],
+                )
+                .await
+                .context(tag)
+        })
+        .await
+    }
+
+    /// Removes the row for good, even when soft delete is enabled.
+    async fn purge(self, client: &C) -> Result<()> {
+        let span = crud_span!("ExtendedCrud.purge", Self::TABLE_NAME, "delete", 1);
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.purge failed";
+            self.before_delete(client).await.context(tag)?;
+            let id = client.as_str(self.primary_key());
+            client
+                .delete_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, vec![id])
+                .await
+                .context(tag)?;
+            self.after_delete(client).await.context(tag)
+        })
+        .await
+    }
+
+    fn primary_key(&self) -> &Self::PrimaryKey;
+
+    /// Runs before the row is written by `create`; an error aborts the create.
+    async fn before_create(&mut self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_create(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs before the row is written by `update` and `update_many`; an error aborts the
+    /// update.
+    async fn before_update(&mut self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_update(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs before `delete` and `purge`; an error aborts them.
+    async fn before_delete(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_delete(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs on every entity returned by `read`, `read_with_deleted` and `read_many`.
+    async fn after_load(&mut self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether `self` is marked as deleted by its soft delete column, which reads check on
+    /// the decoded entity. Entities setting `SOFT_DELETE_NAME` must implement it, as the
+    /// derive does.
+    fn is_deleted(&self) -> bool {
+        false
+    }
+
+    fn version(&self) -> Option<i64> {
+        None
+    }
+
+    fn set_version(&mut self, _version: i64) {}
+}
+
+/// Reads the row `id`, skipping it when soft-deleted unless `with_deleted`.
+async fn read_one<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    id: E::PrimaryKey,
+    with_deleted: bool,
+    tag: &'static str,
+) -> Result<E> {
+    let mut founds = find::<C, E>(client, vec![id], with_deleted, tag).await?;
+    if founds.len() > 1 {
+        anyhow::bail!(format!("{}, Found more than one", tag));
+    }
+    let mut entity = founds
+        .pop()
+        .ok_or_else(|| anyhow!("Not found").context(tag))?;
+    entity.after_load(client).await.context(tag)?;
+    Ok(entity)
+}
+
+/// The rows of `ids`, decoded straight from the rows of the client, without the
+/// soft-deleted ones unless `with_deleted`.
+async fn find<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    ids: Vec<E::PrimaryKey>,
+    with_deleted: bool,
+    tag: &'static str,
+) -> Result<Vec<E>> {
+    let rows = client
+        .find_rows::<E::PrimaryKey>(E::TABLE_NAME, E::PRIMARY_KEY_NAME, ids)
+        .await
+        .context(tag)?;
+    let mut entities = Vec::with_capacity(rows.len());
+    for row in rows {
+        let entity = E::try_from_err(row).map_err(|e| anyhow!(e).context(tag))?;
+        if with_deleted || !entity.is_deleted() {
+            entities.push(entity);
+        }
+    }
+    Ok(entities)
+}
+
+fn pages<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    page_size: usize,
+) -> impl Stream<Item = Result<E>> + Send + '_ {
+    let page_size = page_size.max(1);
+    stream::try_unfold(Some(None), move |after| async move {
+        let tag = "ExtendedCrud.stream failed";
+        let Some(after) = after else {
+            return Ok::<_, anyhow::Error>(None);
+        };
+        let rows = client
+            .find_range_rows(
+                E::TABLE_NAME,
+                E::PRIMARY_KEY_NAME,
+                after.as_ref(),
+                page_size,
+            )
             .await
             .context(tag)?;
-        founds
+        // Backends may cap pages below `page_size`, so only an empty page ends the table.
+        let mut next = None;
+        let mut entities = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut entity = E::try_from_err(row).map_err(|e| anyhow!(e).context(tag))?;
+            let key = serde_json::to_value(entity.primary_key()).context(tag)?;
+            next = Some(Some(key));
+            if entity.is_deleted() {
+                continue;
+            }
+            entity.after_load(client).await.context(tag)?;
+            entities.push(Ok(entity));
+        }
+        Ok(Some((stream::iter(entities), next)))
+    })
+    .try_flatten()
+}
+
+/// Decodes a row of `E` read as JSON through the row type of `C`.
+pub(crate) fn decode<C: Client, E: ExtendedCrud<C>>(
+    value: serde_json::Value,
+) -> Result<E, <C::Row as EntityRow>::Error> {
+    E::try_from_err(C::Row::from_json(value)?)
+}
+
+/// Whether `row` of `E` is marked as deleted by its soft delete column.
+pub(crate) fn is_deleted<C: Client, E: ExtendedCrud<C>>(row: &serde_json::Value) -> bool {
+    E::SOFT_DELETE_NAME.is_some_and(|column| row.get(column).is_some_and(|v| !v.is_null()))
+}
+
+/// The changes marking a row of `E` as deleted now, or as not deleted.
+pub(crate) fn soft_delete_payload<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    deleted: bool,
+) -> serde_json::Result<serde_json::Value> {
+    let now = serde_json::to_value(client.clock().now())?;
+    let mut item = serde_json::Map::new();
+    if let Some(column) = E::SOFT_DELETE_NAME {
+        let value = if deleted {
+            now.clone()
+        } else {
+            serde_json::Value::Null
+        };
+        item.insert(column.to_string(), value);
+    }
+    if let Some(column) = E::UPDATED_AT_NAME {
+        item.insert(column.to_string(), now);
+    }
+    Ok(serde_json::Value::Object(item))
+}
+
+/// Serializes `entity` for a create, with its timestamp columns set to the client clock.
+pub(crate) fn create_payload<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    entity: &E,
+) -> serde_json::Result<serde_json::Value> {
+    let mut item = serde_json::to_value(entity)?;
+    if let Some(row) = item.as_object_mut() {
+        for column in [E::CREATED_AT_NAME, E::UPDATED_AT_NAME]
             .into_iter()
-            .map(|value| Self::try_from_err(value).map_err(|e| anyhow!(e).context(tag)))
-            .collect()
+            .flatten()
+        {
+            row.insert(
+                column.to_string(),
+                serde_json::to_value(client.clock().now())?,
+            );
+        }
+    }
+    Ok(item)
+}
+
+/// Serializes `entity` for an update, with its `updated_at` column set to the client clock
+/// and its `created_at` and soft delete columns left out, so that the stored ones are kept
+/// and only `restore` brings a deleted row back.
+pub(crate) fn update_payload<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    entity: &E,
+) -> serde_json::Result<serde_json::Value> {
+    let mut item = serde_json::to_value(entity)?;
+    if let Some(row) = item.as_object_mut() {
+        for column in [E::CREATED_AT_NAME, E::SOFT_DELETE_NAME]
+            .into_iter()
+            .flatten()
+        {
+            row.remove(column);
+        }
+        if let Some(column) = E::UPDATED_AT_NAME {
+            row.insert(
+                column.to_string(),
+                serde_json::to_value(client.clock().now())?,
+            );
+        }
     }
+    Ok(item)
+}
 
-    async fn update(&self, client: &C) -> Result<()> {
-        let tag = "ExtendedCrud.update failed";
+/// Writes `item` over `entity` only if its row is still at `version`, bumping the
+/// version column.
+async fn update_one<C: Client, E: ExtendedCrud<C>>(client: &C, entity: &mut E) -> Result<()> {
+    entity.before_update(client).await?;
+    entity.validate()?;
+    let item = update_payload(client, entity)?;
+    trace_payload(&item, E::SENSITIVE_FIELDS);
+    if let (Some(column), Some(version)) = (E::VERSION_NAME, entity.version()) {
+        update_if_version(client, entity, item, column, version).await?;
+    } else {
         client
             .update_by_keys(
-                Self::TABLE_NAME,
-                Self::PRIMARY_KEY_NAME,
-                vec![(self.primary_key().to_string(), &self)],
+                E::TABLE_NAME,
+                E::PRIMARY_KEY_NAME,
+                vec![(entity.primary_key().to_string(), item)],
             )
-            .await
-            .context(tag)
+            .await?;
     }
+    entity.after_update(client).await
+}
 
-    async fn update_many(items: Vec<Self>, client: &C) -> Result<()> {
-        let tag = "ExtendedCrud.update_many failed";
-        let items = items
-            .into_iter()
-            .map(|e| (client.as_str(e.primary_key()), e))
-            .collect();
-        client
-            .update_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, items)
-            .await
-            .context(tag)
+async fn update_if_version<C: Client, E: ExtendedCrud<C>>(
+    client: &C,
+    entity: &E,
+    mut item: serde_json::Value,
+    column: &str,
+    version: i64,
+) -> Result<()> {
+    let id = entity.primary_key().to_string();
+    if let Some(row) = item.as_object_mut() {
+        row.insert(column.to_string(), (version + 1).into());
+    }
+    let filters = [
+        (E::PRIMARY_KEY_NAME, id.clone()),
+        (column, version.to_string()),
+    ];
+    let updated = client.update_where(E::TABLE_NAME, &filters, &item).await?;
+    if updated == 0 {
+        return Err(anyhow::Error::new(Conflict {
+            table: E::TABLE_NAME.to_string(),
+            id,
+            version,
+        }));
     }
+    Ok(())
+}
 
-    async fn delete(self, client: &C) -> Result<()> {
-        let tag = "ExtendedCrud.delete failed";
-        let id = client.as_str(self.primary_key());
-        client
-            .delete_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, vec![id])
-            .await
-            .context(tag)
+/// Lifecycle hooks of an entity deriving `ExtendedCrud` with `#[hooks]`, which forwards
+/// the hook methods of `ExtendedCrud` to this trait.
+#[async_trait]
+pub trait CrudHooks<C: Client>: Send + Sync {
+    async fn before_create(&mut self, _client: &C) -> Result<()> {
+        Ok(())
     }
 
-    async fn delete_many(ids: Vec<Self::PrimaryKey>, client: &C) -> Result<()> {
-        let tag = "ExtendedCrud.delete_many failed";
-        let ids = ids.into_iter().map(|e| client.as_str(e)).collect();
-        client
-            .delete_by_keys(Self::TABLE_NAME, Self::PRIMARY_KEY_NAME, ids)
-            .await
-            .context(tag)
+    async fn after_create(&self, _client: &C) -> Result<()> {
+        Ok(())
     }
 
-    fn primary_key(&self) -> &Self::PrimaryKey;
+    async fn before_update(&mut self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_update(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn before_delete(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_delete(&self, _client: &C) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_load(&mut self, _client: &C) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub trait TryFromError<T, E>: Sized {
@@ -112,5 +589,302 @@ pub trait PartialEntity<T>: Serialize + Send + Sync + 'static {
 
     fn apply_to(&self, original: &T) -> T;
 
+    /// Like `apply_to`, but rejects a result failing validation.
+    fn try_apply_to(&self, original: &T) -> Result<T, ValidationErrors>
+    where
+        T: Validate,
+    {
+        let applied = self.apply_to(original);
+        applied.validate()?;
+        Ok(applied)
+    }
+
     fn primary_key(&self) -> Option<Self::PrimaryKey>;
+
+    /// The fields of `after` whose value differs from `before`, the others unset.
+    /// The default implementation compares the serialized entities column by column.
+    fn diff(before: &T, after: &T) -> Self
+    where
+        T: Serialize,
+        Self: DeserializeOwned,
+    {
+        let before = serde_json::to_value(before).unwrap_or_default();
+        let after = serde_json::to_value(after).unwrap_or_default();
+        let changes = after
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(column, value)| before.get(column.as_str()) != Some(value))
+            .map(|(column, value)| (column.clone(), value.clone()))
+            .collect();
+        serde_json::from_value(serde_json::Value::Object(changes)).unwrap_or_else(|_| Self::new())
+    }
+
+    /// The columns of the fields set on `self`, with their values.
+    /// The default implementation keeps the non-null columns of `self` serialized.
+    fn changes(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(mut changes)) => {
+                changes.retain(|_, value| !value.is_null());
+                changes
+            }
+            _ => serde_json::Map::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use crate::entity::column::Column;
+    use ext_crud_derive::ExtendedCrud;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("documents")]
+    struct Document {
+        id: String,
+        body: String,
+        #[version]
+        version: i64,
+    }
+
+    #[tokio::test]
+    async fn rejects_updates_of_stale_versions() {
+        let client = MemoryClient::default();
+        let mut document = Document {
+            id: "doc".to_string(),
+            body: "draft".to_string(),
+            version: 0,
+        };
+        document.clone().create(&client).await.unwrap();
+        let mut stale = document.clone();
+
+        document.body = "first".to_string();
+        document.update_versioned(&client).await.unwrap();
+        assert_eq!(document.version, 1);
+        document.body = "second".to_string();
+        document.update_versioned(&client).await.unwrap();
+
+        stale.body = "lost".to_string();
+        let error = stale.update(&client).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Conflict>(),
+            Some(&Conflict {
+                table: "documents".to_string(),
+                id: "doc".to_string(),
+                version: 0,
+            })
+        );
+        let stored = Document::read(&client, "doc".to_string()).await.unwrap();
+        assert_eq!((stored.body.as_str(), stored.version), ("second", 2));
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("notes")]
+    struct Note {
+        id: String,
+        #[soft_delete]
+        deleted_at: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn soft_deletes_restores_and_purges() {
+        let client = MemoryClient::default();
+        for id in ["a", "b"] {
+            let note = Note {
+                id: id.to_string(),
+                deleted_at: None,
+            };
+            note.create(&client).await.unwrap();
+        }
+        let note = Note::read(&client, "a".to_string()).await.unwrap();
+        note.clone().delete(&client).await.unwrap();
+
+        assert!(Note::read(&client, "a".to_string()).await.is_err());
+        let ids = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(
+            Note::read_many(ids.clone(), &client).await.unwrap().len(),
+            1
+        );
+        let deleted = Note::read_with_deleted(&client, "a".to_string())
+            .await
+            .unwrap();
+        assert!(deleted.deleted_at.is_some());
+        assert_eq!(client.rows("notes").len(), 2);
+
+        Note::restore(&client, "a".to_string()).await.unwrap();
+        assert_eq!(
+            Note::read_many(ids.clone(), &client).await.unwrap().len(),
+            2
+        );
+
+        note.purge(&client).await.unwrap();
+        assert!(Note::read_with_deleted(&client, "a".to_string())
+            .await
+            .is_err());
+        assert_eq!(client.rows("notes").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn updates_keep_rows_deleted() {
+        let client = MemoryClient::default();
+        let note = Note {
+            id: "a".to_string(),
+            deleted_at: None,
+        };
+        note.create(&client).await.unwrap();
+        let mut stale = Note::read(&client, "a".to_string()).await.unwrap();
+        stale.clone().delete(&client).await.unwrap();
+
+        stale.update(&client).await.unwrap();
+        assert!(Note::read(&client, "a".to_string()).await.is_err());
+        Note::restore(&client, "a".to_string()).await.unwrap();
+        assert!(Note::read(&client, "a".to_string()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn streams_rows_by_pages() {
+        let client = MemoryClient::default();
+        for id in ["e", "a", "d", "b", "c"] {
+            let note = Note {
+                id: id.to_string(),
+                deleted_at: None,
+            };
+            note.create(&client).await.unwrap();
+        }
+        Note::read(&client, "b".to_string())
+            .await
+            .unwrap()
+            .delete(&client)
+            .await
+            .unwrap();
+
+        let ids = Note::stream(&client, 2)
+            .map_ok(|note| note.id)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(ids, ["a", "c", "d", "e"]);
+        let pages = client
+            .calls()
+            .into_iter()
+            .filter(|call| call.starts_with("find_range"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pages,
+            [
+                "find_range notes - 2",
+                "find_range notes \"b\" 2",
+                "find_range notes \"d\" 2",
+                "find_range notes \"e\" 2"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn queries_rows_by_column_filters() {
+        let client = MemoryClient::default();
+        for id in ["e", "a", "d", "b", "c"] {
+            let note = Note {
+                id: id.to_string(),
+                deleted_at: None,
+            };
+            note.create(&client).await.unwrap();
+        }
+        Note::read(&client, "d".to_string())
+            .await
+            .unwrap()
+            .delete(&client)
+            .await
+            .unwrap();
+
+        let ids = |notes: Vec<Note>| notes.into_iter().map(|note| note.id).collect::<Vec<_>>();
+        let notes = Note::query()
+            .filter(Note::ID.gt("a"))
+            .filter(Note::ID.neq("c"))
+            .order(Note::ID.desc())
+            .limit(2)
+            .fetch(&client)
+            .await
+            .unwrap();
+        assert_eq!(ids(notes), ["e", "b"]);
+        let notes = Note::query()
+            .filter(Note::ID.is_in(["a", "d"]))
+            .with_deleted()
+            .order(Note::ID.asc())
+            .fetch(&client)
+            .await
+            .unwrap();
+        assert_eq!(ids(notes), ["a", "d"]);
+        assert!(client.calls().contains(&"find_where notes 3".to_string()));
+        let notes = Note::query()
+            .filter(Note::DELETED_AT.neq(None))
+            .with_deleted()
+            .fetch(&client)
+            .await
+            .unwrap();
+        assert_eq!(ids(notes), ["d"]);
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("accounts")]
+    #[hooks]
+    struct Account {
+        id: String,
+        email: String,
+        #[serde(skip)]
+        loaded: bool,
+    }
+
+    #[async_trait]
+    impl CrudHooks<MemoryClient> for Account {
+        async fn before_create(&mut self, _client: &MemoryClient) -> Result<()> {
+            self.email = self.email.trim().to_lowercase();
+            Ok(())
+        }
+
+        async fn after_create(&self, client: &MemoryClient) -> Result<()> {
+            let event = serde_json::json!({ "account_created": self.id });
+            client.create("events", &event).await
+        }
+
+        async fn before_update(&mut self, client: &MemoryClient) -> Result<()> {
+            if !self.email.contains('@') {
+                anyhow::bail!("invalid email: {}", self.email);
+            }
+            CrudHooks::before_create(self, client).await
+        }
+
+        async fn after_load(&mut self, _client: &MemoryClient) -> Result<()> {
+            self.loaded = true;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_lifecycle_hooks() {
+        let client = MemoryClient::default();
+        let account = Account {
+            id: "a".to_string(),
+            email: " Alice@Example.com ".to_string(),
+            loaded: false,
+        };
+        account.create(&client).await.unwrap();
+        assert_eq!(client.rows("events").len(), 1);
+
+        let mut account = Account::read(&client, "a".to_string()).await.unwrap();
+        assert_eq!(account.email, "alice@example.com");
+        assert!(account.loaded);
+
+        account.email = "Bob@Example.com".to_string();
+        account.update(&client).await.unwrap();
+        assert_eq!(account.email, "bob@example.com");
+        assert!(account.loaded);
+        account.email = "not an email".to_string();
+        assert!(account.update(&client).await.is_err());
+        let account = Account::read(&client, "a".to_string()).await.unwrap();
+        assert_eq!(account.email, "bob@example.com");
+    }
 }
@@ -1,5 +1,19 @@
+pub mod column;
 pub mod extend;
+pub mod query;
+pub mod relation;
+pub mod row;
+pub mod validate;
+pub mod view;
 
+pub use column::{Column, Filter, FilterOp, Order};
+pub use extend::CrudHooks;
 pub use extend::ExtendedCrud;
 pub use extend::PartialEntity;
 pub use extend::TryFromError;
+pub use query::Query;
+pub use relation::load_belongs_to;
+pub use relation::load_has_many;
+pub use row::{EntityRow, JsonRow};
+pub use validate::{Validate, ValidationErrors};
+pub use view::ExtendedView;
@@ -0,0 +1,92 @@
+use std::marker::PhantomData;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::clients::client::Client;
+use crate::entity::column::{Filter, FilterOp, Order};
+use crate::entity::extend::ExtendedCrud;
+use crate::telemetry::{crud_span, instrumented};
+
+/// A filtered read of the table of `E`, built with `ExtendedCrud::query` and the
+/// column constants of `E`, e.g. `User::query().filter(User::AGE.gt(30u32))`.
+pub struct Query<C, E> {
+    filters: Vec<Filter<E>>,
+    order: Vec<Order<E>>,
+    limit: Option<usize>,
+    with_deleted: bool,
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<C: Client, E: ExtendedCrud<C>> Query<C, E> {
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            order: Vec::new(),
+            limit: None,
+            with_deleted: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Keeps the rows matching `filter` as well as every previous filter.
+    pub fn filter(mut self, filter: Filter<E>) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Sorts by `order` after the previous sort keys.
+    pub fn order(mut self, order: Order<E>) -> Self {
+        self.order.push(order);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Also returns soft-deleted rows.
+    pub fn with_deleted(mut self) -> Self {
+        self.with_deleted = true;
+        self
+    }
+
+    /// Reads the matching rows with `Client::find_where_rows`.
+    pub async fn fetch(self, client: &C) -> Result<Vec<E>> {
+        let span = crud_span!("ExtendedCrud.query", E::TABLE_NAME, "read", 0);
+        instrumented(span, async move {
+            let tag = "ExtendedCrud.query failed";
+            let mut filters = self
+                .filters
+                .into_iter()
+                .map(|filter| (filter.column, filter.op, filter.value))
+                .collect::<Vec<_>>();
+            if let (false, Some(column)) = (self.with_deleted, E::SOFT_DELETE_NAME) {
+                filters.push((column, FilterOp::IsNull, serde_json::Value::Null));
+            }
+            let order = self
+                .order
+                .iter()
+                .map(|order| (order.column, order.ascending))
+                .collect::<Vec<_>>();
+            let rows = client
+                .find_where_rows(E::TABLE_NAME, &filters, &order, self.limit)
+                .await
+                .context(tag)?;
+            let mut entities = Vec::with_capacity(rows.len());
+            for row in rows {
+                let mut entity = E::try_from_err(row).map_err(|e| anyhow!(e).context(tag))?;
+                entity.after_load(client).await.context(tag)?;
+                entities.push(entity);
+            }
+            Ok(entities)
+        })
+        .await
+    }
+}
+
+impl<C: Client, E: ExtendedCrud<C>> Default for Query<C, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+
+use crate::clients::client::Client;
+use crate::entity::extend::{decode, is_deleted, ExtendedCrud};
+
+/// Loads the children of every parent with a single `find_by_keys` call on `foreign_key`.
+/// The result is aligned with `parents` and skips soft-deleted children.
+pub async fn load_has_many<C, P, R>(
+    client: &C,
+    parents: &[P],
+    foreign_key: &str,
+) -> Result<Vec<Vec<R>>>
+where
+    C: Client,
+    P: ExtendedCrud<C>,
+    R: ExtendedCrud<C>,
+{
+    let tag = "load_has_many failed";
+    if parents.is_empty() {
+        return Ok(vec![]);
+    }
+    let ids = parents.iter().map(|p| p.primary_key()).collect::<Vec<_>>();
+    let founds = client
+        .find_by_keys(R::TABLE_NAME, foreign_key, ids)
+        .await
+        .context(tag)?;
+
+    let mut grouped: HashMap<String, Vec<R>> = HashMap::new();
+    for value in founds.into_iter().filter(|v| !is_deleted::<C, R>(v)) {
+        let key = value
+            .get(foreign_key)
+            .map(|v| v.to_string())
+            .ok_or_else(|| anyhow!("Missing column {}", foreign_key).context(tag))?;
+        let child = decode::<C, R>(value).map_err(|e| anyhow!(e).context(tag))?;
+        grouped.entry(key).or_default().push(child);
+    }
+    parents
+        .iter()
+        .map(|p| {
+            let key = key_string(p.primary_key()).context(tag)?;
+            Ok(grouped.remove(&key).unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Loads the parent referenced by every item with a single `find_rows` call on the
+/// parent primary key. The result is aligned with `items`, soft-deleted parents are `None`.
+pub async fn load_belongs_to<C, P, R, F>(client: &C, items: &[P], key: F) -> Result<Vec<Option<R>>>
+where
+    C: Client,
+    R: ExtendedCrud<C> + Clone,
+    F: Fn(&P) -> R::PrimaryKey,
+{
+    let tag = "load_belongs_to failed";
+    if items.is_empty() {
+        return Ok(vec![]);
+    }
+    let keys = items
+        .iter()
+        .map(|item| key_string(&key(item)).map(|k| (k, key(item))))
+        .collect::<Result<Vec<_>>>()
+        .context(tag)?;
+    let mut ids = HashMap::new();
+    for (k, id) in &keys {
+        ids.entry(k.clone()).or_insert(id);
+    }
+    let founds = client
+        .find_rows(
+            R::TABLE_NAME,
+            R::PRIMARY_KEY_NAME,
+            ids.into_values().collect(),
+        )
+        .await
+        .context(tag)?;
+
+    let mut parents = HashMap::new();
+    for row in founds {
+        let parent = R::try_from_err(row).map_err(|e| anyhow!(e).context(tag))?;
+        if !parent.is_deleted() {
+            parents.insert(key_string(parent.primary_key()).context(tag)?, parent);
+        }
+    }
+    Ok(keys.iter().map(|(k, _)| parents.get(k).cloned()).collect())
+}
+
+fn key_string<K: Serialize>(key: &K) -> Result<String> {
+    Ok(serde_json::to_value(key)?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use crate::entity::column::Column;
+    use crate::entity::extend::TryFromError;
+    use crate::entity::row::EntityRow;
+    use crate::entity::validate::{Validate, ValidationErrors};
+    use ext_crud_derive::ExtendedCrud;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("customers")]
+    #[has_many(Address, foreign_key = "customer_id")]
+    #[has_many(Category, foreign_key = "owner_id")]
+    struct Customer {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("addresses")]
+    #[belongs_to(Customer, key = "customer_id")]
+    struct Address {
+        id: i64,
+        customer_id: i64,
+        #[soft_delete]
+        deleted_at: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("categories")]
+    struct Category {
+        id: i64,
+        owner_id: i64,
+    }
+
+    async fn client() -> MemoryClient {
+        let client = MemoryClient::default();
+        for (id, name) in [(1, "john"), (2, "jane")] {
+            let customer = Customer {
+                id,
+                name: name.to_string(),
+            };
+            customer.create(&client).await.unwrap();
+        }
+        for (id, customer_id, deleted_at) in [(1, 1, None), (2, 1, Some("now")), (3, 2, None)] {
+            let address = Address {
+                id,
+                customer_id,
+                deleted_at: deleted_at.map(str::to_string),
+            };
+            client.create("addresses", &address).await.unwrap();
+        }
+        let category = Category { id: 1, owner_id: 2 };
+        category.create(&client).await.unwrap();
+        client
+    }
+
+    #[tokio::test]
+    async fn loads_relations_through_generated_accessors() {
+        let client = client().await;
+        let john = Customer::read(&client, 1).await.unwrap();
+        let ids = |addresses: &[Address]| addresses.iter().map(|a| a.id).collect::<Vec<_>>();
+        assert_eq!(ids(&john.addresses(&client).await.unwrap()), [1]);
+        assert!(john.categories(&client).await.unwrap().is_empty());
+
+        let address = Address::read(&client, 3).await.unwrap();
+        assert_eq!(address.customer(&client).await.unwrap().name, "jane");
+    }
+
+    #[tokio::test]
+    async fn eager_loads_relations_in_one_read() {
+        let client = client().await;
+        let customers = Customer::read_many(vec![1, 2], &client).await.unwrap();
+        let before = client.calls().len();
+        let loaded = Customer::with_addresses(customers, &client).await.unwrap();
+        assert_eq!(client.calls().len(), before + 1);
+        let summary = loaded
+            .iter()
+            .map(|(customer, addresses)| (customer.id, addresses.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, [(1, 1), (2, 1)]);
+
+        let addresses = vec![
+            Address::read(&client, 1).await.unwrap(),
+            Address::read(&client, 3).await.unwrap(),
+        ];
+        let loaded = Address::with_customer(addresses, &client).await.unwrap();
+        let names = loaded
+            .iter()
+            .map(|(_, customer)| customer.as_ref().map(|c| c.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [Some("john"), Some("jane")]);
+    }
+}
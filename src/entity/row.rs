@@ -0,0 +1,79 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+/// The native representation of the rows a `Client` reads, e.g. a SQL row or an encoded
+/// blob, which entities decode themselves from with `TryFromError`.
+pub trait EntityRow: Sized + Send + 'static {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Deserializes the row; the derived `TryFromError` impls call it.
+    fn decode<T: DeserializeOwned>(self) -> Result<T, Self::Error>;
+
+    /// Converts a row read as JSON, by the paths inspecting rows as `serde_json::Value`.
+    fn from_json(value: serde_json::Value) -> Result<Self, Self::Error>;
+}
+
+impl EntityRow for serde_json::Value {
+    type Error = serde_json::Error;
+
+    fn decode<T: DeserializeOwned>(self) -> Result<T, Self::Error> {
+        serde_json::from_value(self)
+    }
+
+    fn from_json(value: serde_json::Value) -> Result<Self, Self::Error> {
+        Ok(value)
+    }
+}
+
+/// A JSON row kept as its unparsed text, decoded straight into entities without building
+/// a `serde_json::Value` first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonRow(Box<RawValue>);
+
+impl JsonRow {
+    pub fn get(&self) -> &str {
+        self.0.get()
+    }
+}
+
+impl EntityRow for JsonRow {
+    type Error = serde_json::Error;
+
+    fn decode<T: DeserializeOwned>(self) -> Result<T, Self::Error> {
+        serde_json::from_str(self.0.get())
+    }
+
+    fn from_json(value: serde_json::Value) -> Result<Self, Self::Error> {
+        serde_json::value::to_raw_value(&value).map(JsonRow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    #[test]
+    fn decodes_json_rows() {
+        let rows: Vec<JsonRow> =
+            serde_json::from_slice(br#"[{"id":1,"name":"john"},{"id":2,"name":"jane"}]"#).unwrap();
+        assert_eq!(rows[1].get(), r#"{"id":2,"name":"jane"}"#);
+        let users = rows
+            .into_iter()
+            .map(EntityRow::decode::<User>)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(users[0].name, "john");
+
+        let row = JsonRow::from_json(json!({ "id": 3, "name": "jim" })).unwrap();
+        assert_eq!(row.decode::<User>().unwrap().id, 3);
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+/// Checks run on entities before `ExtendedCrud` writes them, generated from the
+/// `#[validate(...)]` field attributes of the derive.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Column of the invalid field.
+    pub field: String,
+    /// Name of the failed check: `length`, `email`, `range` or `custom`.
+    pub code: String,
+    pub message: String,
+}
+
+/// Every field error of an entity, kept in the `anyhow::Error` chain of rejected writes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+/// Values whose length `ValidationErrors::check_length` can check, in characters for
+/// strings and in items for collections.
+pub trait HasLength {
+    fn length(&self) -> usize;
+}
+
+impl HasLength for str {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl HasLength for String {
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+impl<T> HasLength for [T] {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> HasLength for Vec<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn add(&mut self, field: &str, code: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// `Ok` when no error was added.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    pub fn check_length<V: HasLength + ?Sized>(
+        &mut self,
+        field: &str,
+        value: &V,
+        min: Option<usize>,
+        max: Option<usize>,
+    ) {
+        let length = value.length();
+        if let Some(min) = min.filter(|min| length < *min) {
+            self.add(field, "length", format!("must be at least {} long", min));
+        }
+        if let Some(max) = max.filter(|max| length > *max) {
+            self.add(field, "length", format!("must be at most {} long", max));
+        }
+    }
+
+    pub fn check_email(&mut self, field: &str, value: &str) {
+        let valid = match value.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.split('.').count() > 1
+                    && domain.split('.').all(|label| !label.is_empty())
+                    && !value.chars().any(char::is_whitespace)
+            }
+            None => false,
+        };
+        if !valid {
+            self.add(field, "email", "must be a valid email address");
+        }
+    }
+
+    pub fn check_range(&mut self, field: &str, value: f64, min: Option<f64>, max: Option<f64>) {
+        if let Some(min) = min.filter(|min| value < *min) {
+            self.add(field, "range", format!("must be at least {}", min));
+        }
+        if let Some(max) = max.filter(|max| value > *max) {
+            self.add(field, "range", format!("must be at most {}", max));
+        }
+    }
+
+    /// Records the error message returned by a custom validator function.
+    pub fn check_custom(&mut self, field: &str, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.add(field, "custom", message);
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Validation failed")?;
+        for (i, error) in self.errors.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            write!(f, "{}{} {}", separator, error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_field_errors() {
+        let mut errors = ValidationErrors::new();
+        errors.check_length("name", "", Some(1), Some(255));
+        errors.check_length("name", "Jo", Some(1), Some(255));
+        errors.check_email("email", "john@example.com");
+        errors.check_email("email", "john@example");
+        errors.check_range("age", 200.0, Some(0.0), Some(150.0));
+        errors.check_custom("name", Err("is reserved".to_string()));
+
+        let codes = errors
+            .errors
+            .iter()
+            .map(|e| (e.field.as_str(), e.code.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [
+                ("name", "length"),
+                ("email", "email"),
+                ("age", "range"),
+                ("name", "custom")
+            ]
+        );
+        assert_eq!(
+            errors.to_string(),
+            "Validation failed: name must be at least 1 long, email must be a valid email \
+             address, age must be at most 150, name is reserved"
+        );
+        assert!(ValidationErrors::new().into_result().is_ok());
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+
+use crate::clients::client::Client;
+use crate::entity::extend::{is_deleted, ExtendedCrud, TryFromError};
+use crate::telemetry::{crud_span, instrumented};
+
+/// A read-only projection of an `ExtendedCrud` entity, fetching only `COLUMNS`.
+/// Soft-deleted rows of the entity are skipped.
+#[async_trait]
+pub trait ExtendedView<C: Client>:
+    Sized
+    + DeserializeOwned
+    + Send
+    + Sync
+    + 'static
+    + TryFromError<serde_json::Value, serde_json::Error>
+{
+    type Entity: ExtendedCrud<C>;
+
+    const COLUMNS: &'static [&'static str];
+
+    async fn read(client: &C, id: <Self::Entity as ExtendedCrud<C>>::PrimaryKey) -> Result<Self> {
+        let span = crud_span!("ExtendedView.read", Self::Entity::TABLE_NAME, "read", 1);
+        instrumented(span, async move {
+            let tag = "ExtendedView.read failed";
+            let founds = client
+                .find_by_keys_select(
+                    Self::Entity::TABLE_NAME,
+                    Self::Entity::PRIMARY_KEY_NAME,
+                    vec![id],
+                    &select_columns::<C, Self>(),
+                )
+                .await
+                .context(tag)?;
+            let mut founds = visible_rows::<C, Self>(founds);
+            if founds.len() > 1 {
+                anyhow::bail!(format!("{}, Found more than one", tag));
+            }
+            let value = founds
+                .pop()
+                .ok_or_else(|| anyhow!("Not found").context(tag))?;
+            Self::try_from_err(value).map_err(|e| anyhow!(e).context(tag))
+        })
+        .await
+    }
+
+    async fn read_many(
+        ids: Vec<<Self::Entity as ExtendedCrud<C>>::PrimaryKey>,
+        client: &C,
+    ) -> Result<Vec<Self>> {
+        let span = crud_span!(
+            "ExtendedView.read_many",
+            Self::Entity::TABLE_NAME,
+            "read",
+            ids.len()
+        );
+        instrumented(span, async move {
+            let tag = "ExtendedView.read_many failed";
+            let founds = client
+                .find_by_keys_select(
+                    Self::Entity::TABLE_NAME,
+                    Self::Entity::PRIMARY_KEY_NAME,
+                    ids,
+                    &select_columns::<C, Self>(),
+                )
+                .await
+                .context(tag)?;
+            visible_rows::<C, Self>(founds)
+                .into_iter()
+                .map(|value| Self::try_from_err(value).map_err(|e| anyhow!(e).context(tag)))
+                .collect()
+        })
+        .await
+    }
+}
+
+/// `COLUMNS`, plus the soft delete column of the entity to tell deleted rows apart.
+fn select_columns<C: Client, V: ExtendedView<C>>() -> Vec<&'static str> {
+    let mut columns = V::COLUMNS.to_vec();
+    if let Some(column) = V::Entity::SOFT_DELETE_NAME {
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    columns
+}
+
+/// Drops the soft-deleted rows and the columns `select_columns` added.
+fn visible_rows<C: Client, V: ExtendedView<C>>(
+    founds: Vec<serde_json::Value>,
+) -> Vec<serde_json::Value> {
+    founds
+        .into_iter()
+        .filter(|value| !is_deleted::<C, V::Entity>(value))
+        .map(|mut value| {
+            if let (Some(column), Some(row)) = (V::Entity::SOFT_DELETE_NAME, value.as_object_mut())
+            {
+                if !V::COLUMNS.contains(&column) {
+                    row.remove(column);
+                }
+            }
+            value
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::memory::MemoryClient;
+    use crate::entity::column::Column;
+    use crate::entity::row::EntityRow;
+    use crate::entity::validate::{Validate, ValidationErrors};
+    use ext_crud_derive::{ExtendedCrud, ExtendedView};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ExtendedCrud)]
+    #[table_name("customers")]
+    struct Customer {
+        id: i64,
+        name: String,
+        email: String,
+        #[soft_delete]
+        deleted_at: Option<String>,
+    }
+
+    /// Rejects unknown fields, so that columns outside the view fail the read.
+    #[derive(Debug, PartialEq, Deserialize, ExtendedView)]
+    #[view_of(Customer)]
+    #[serde(deny_unknown_fields)]
+    struct CustomerName {
+        id: i64,
+        name: String,
+    }
+
+    async fn client() -> MemoryClient {
+        let client = MemoryClient::default();
+        for (id, name, deleted_at) in [(1, "john", None), (2, "jane", Some("now"))] {
+            let customer = Customer {
+                id,
+                name: name.to_string(),
+                email: format!("{}@example.com", name),
+                deleted_at: deleted_at.map(str::to_string),
+            };
+            client.create("customers", &customer).await.unwrap();
+        }
+        client
+    }
+
+    #[tokio::test]
+    async fn reads_only_the_view_columns() {
+        let client = client().await;
+        let name = CustomerName::read(&client, 1).await.unwrap();
+        assert_eq!(
+            name,
+            CustomerName {
+                id: 1,
+                name: "john".to_string(),
+            }
+        );
+        assert_eq!(
+            select_columns::<MemoryClient, CustomerName>(),
+            ["id", "name", "deleted_at"]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_soft_deleted_rows() {
+        let client = client().await;
+        assert!(CustomerName::read(&client, 2).await.is_err());
+        let names = CustomerName::read_many(vec![1, 2], &client).await.unwrap();
+        assert_eq!(
+            names.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            ["john"]
+        );
+    }
+}
@@ -1,19 +1,69 @@
 pub mod entity;
 
+pub use entity::column::{Column, Filter, FilterOp, Order};
+
+pub use entity::extend::CrudHooks;
+
 pub use entity::extend::ExtendedCrud;
 
 pub use entity::extend::PartialEntity;
 
 pub use entity::extend::TryFromError;
 
+pub use entity::query::Query;
+
+pub use entity::relation::{load_belongs_to, load_has_many};
+
+pub use entity::row::{EntityRow, JsonRow};
+
+pub use entity::validate::{FieldError, Validate, ValidationErrors};
+
+pub use entity::view::ExtendedView;
+
 pub mod clients;
 
+pub use clients::audit::{
+    AuditAction, AuditConfig, AuditEntry, AuditLayer, AuditSink, AuditingClient, ChannelSink,
+    FileSink, TableSink,
+};
+
+pub use clients::cache::{CacheConfig, CacheLayer, CachedClient};
+
 pub use clients::client::Client;
 
+pub use clients::clock::{Clock, ClockLayer, ClockedClient, FixedClock, SystemClock};
+
+pub use clients::coalesce::{CoalesceLayer, CoalescingClient};
+
+pub use clients::error::{AuditFailed, Conflict, StatusError};
+
+pub use clients::events::{ChangeOp, EntityChanged, EventClient, EventLayer};
+
+pub use clients::layer::{layer_fn, ClientBuilder, Layer, LayerFn};
+
+#[cfg(feature = "metrics")]
+pub use clients::metrics::{MetricsClient, MetricsLayer};
+pub use clients::outbox::{EventSink, OutboxClient, OutboxEvent, OutboxLayer, OutboxRelay};
+
+pub use clients::retry::{RetryLayer, RetryPolicy, RetryingClient};
+
+pub use clients::transaction::{ExpectedVersion, Operation, Transaction};
+
 pub mod supabase;
 
+pub use supabase::builder::SupabaseClientBuilder;
+
+#[cfg(feature = "realtime")]
+pub use supabase::realtime::{Change, RealtimeClient, Subscription};
+
+pub use supabase::rpc::{RpcMethod, RpcOptions};
+
 pub use supabase::supabase::SupabaseClient;
 
+pub mod telemetry;
+
+pub use telemetry::redact;
+
 #[cfg(feature = "derive")]
 pub use ext_crud_derive::*;
 
@@ -21,11 +71,42 @@ pub use ext_crud_derive::*;
 /// Easy to use in the client code, just import this module with
 /// `use ext_crud_rs::prelude::*;`
 pub mod prelude {
+    pub use crate::clients::audit::{
+        AuditAction, AuditConfig, AuditEntry, AuditLayer, AuditSink, AuditingClient, ChannelSink,
+        FileSink, TableSink,
+    };
+    pub use crate::clients::cache::{CacheConfig, CacheLayer, CachedClient};
     pub use crate::clients::client::Client;
+    pub use crate::clients::clock::{Clock, ClockLayer, ClockedClient, FixedClock, SystemClock};
+    pub use crate::clients::coalesce::{CoalesceLayer, CoalescingClient};
+    pub use crate::clients::error::{AuditFailed, Conflict, StatusError};
+    pub use crate::clients::events::{ChangeOp, EntityChanged, EventClient, EventLayer};
+    pub use crate::clients::layer::{layer_fn, ClientBuilder, Layer, LayerFn};
+    #[cfg(feature = "metrics")]
+    pub use crate::clients::metrics::{MetricsClient, MetricsLayer};
+    pub use crate::clients::outbox::{
+        EventSink, OutboxClient, OutboxEvent, OutboxLayer, OutboxRelay,
+    };
+    pub use crate::clients::retry::{RetryLayer, RetryPolicy, RetryingClient};
+    pub use crate::clients::transaction::{ExpectedVersion, Operation, Transaction};
+    pub use crate::entity::column::{Column, Filter, FilterOp, Order};
+    pub use crate::entity::extend::CrudHooks;
     pub use crate::entity::extend::ExtendedCrud;
     pub use crate::entity::extend::PartialEntity;
     pub use crate::entity::extend::TryFromError;
+    pub use crate::entity::query::Query;
+    pub use crate::entity::relation::{load_belongs_to, load_has_many};
+    pub use crate::entity::row::{EntityRow, JsonRow};
+    pub use crate::entity::validate::{FieldError, Validate, ValidationErrors};
+    pub use crate::entity::view::ExtendedView;
+    pub use crate::supabase::builder::SupabaseClientBuilder;
+    #[cfg(feature = "realtime")]
+    pub use crate::supabase::realtime::{Change, RealtimeClient, Subscription};
+    pub use crate::supabase::rpc::{RpcMethod, RpcOptions};
     pub use crate::supabase::supabase::SupabaseClient;
+    pub use crate::telemetry::redact;
+
+    pub use async_trait::async_trait;
 
     #[cfg(feature = "derive")]
     pub use ext_crud_derive::*;
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::supabase::supabase::SupabaseClient;
+
+/// Default path of the PostgREST API behind the Supabase gateway.
+pub const DEFAULT_REST_PATH: &str = "/rest/v1";
+
+/// Configures a `SupabaseClient`, started from `SupabaseClient::builder`.
+#[derive(Debug, Clone)]
+pub struct SupabaseClientBuilder {
+    url: String,
+    key: String,
+    rest_path: String,
+    schema: Option<String>,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    user_agent: Option<String>,
+}
+
+impl SupabaseClientBuilder {
+    /// `key` is sent as `apikey` and bearer token; leave it empty for a PostgREST
+    /// without authentication.
+    pub fn new(url: &str, key: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            key: key.to_string(),
+            rest_path: DEFAULT_REST_PATH.to_string(),
+            schema: None,
+            headers: vec![],
+            timeout: None,
+            connect_timeout: None,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            user_agent: None,
+        }
+    }
+
+    /// Path appended to the url, e.g. `""` for a self-hosted PostgREST at the root.
+    pub fn rest_path(mut self, rest_path: &str) -> Self {
+        self.rest_path = rest_path.to_string();
+        self
+    }
+
+    /// Postgres schema sent as `Accept-Profile`/`Content-Profile`.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<SupabaseClient> {
+        let tag = "SupabaseClientBuilder.build failed";
+        let endpoint = format!("{}{}", self.url.trim_end_matches('/'), self.rest_path);
+
+        let mut headers = HeaderMap::new();
+        if !self.key.is_empty() {
+            headers.insert("apikey", header_value(&self.key).context(tag)?);
+            headers.insert(
+                "Authorization",
+                header_value(&format!("Bearer {}", self.key)).context(tag)?,
+            );
+        }
+        for (name, value) in &self.headers {
+            let name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|e| anyhow!(e).context(tag))?;
+            headers.insert(name, header_value(value).context(tag)?);
+        }
+
+        let mut http = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            http = http.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            http = http.pool_max_idle_per_host(max);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            http = http.user_agent(user_agent);
+        }
+        let http = http.build().map_err(|e| anyhow!(e).context(tag))?;
+
+        SupabaseClient::from_parts(endpoint, self.schema, headers, http).context(tag)
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|e| anyhow!(e))
+}
@@ -1 +1,7 @@
+pub mod builder;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod rpc;
+// Keeps the public `supabase::supabase::SupabaseClient` path, which clippy flags.
+#[allow(clippy::module_inception)]
 pub mod supabase;
@@ -0,0 +1,498 @@
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, warn};
+
+use crate::clients::client::Client;
+use crate::entity::extend::{ExtendedCrud, TryFromError};
+
+/// Path of the Realtime websocket behind the Supabase gateway.
+pub const DEFAULT_REALTIME_PATH: &str = "/realtime/v1/websocket";
+
+/// Interval of the Phoenix heartbeats keeping the connection open. The server must answer
+/// each heartbeat before the next one is due.
+pub const DEFAULT_HEARTBEAT: Duration = Duration::from_secs(30);
+
+/// How long `RealtimeClient::subscribe` waits for the server to accept the join.
+pub const DEFAULT_JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A change of a row of the subscribed table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+    Insert(T),
+    /// `old` only holds the primary key unless the table has `REPLICA IDENTITY FULL`.
+    Update {
+        old: serde_json::Value,
+        new: T,
+    },
+    /// `old` only holds the primary key unless the table has `REPLICA IDENTITY FULL`.
+    Delete {
+        old: serde_json::Value,
+    },
+}
+
+/// Connects to the Supabase Realtime websocket and streams the changes of tables.
+#[derive(Debug, Clone)]
+pub struct RealtimeClient {
+    endpoint: String,
+    key: String,
+    schema: String,
+    heartbeat: Duration,
+    join_timeout: Duration,
+}
+
+impl RealtimeClient {
+    /// `url` is the project url also given to `SupabaseClient::builder`.
+    pub fn new(url: &str, key: &str) -> Self {
+        let url = url.trim_end_matches('/');
+        let url = match url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some(("http", rest)) => format!("ws://{}", rest),
+            _ => url.to_string(),
+        };
+        Self::with_endpoint(&format!("{}{}", url, DEFAULT_REALTIME_PATH), key)
+    }
+
+    /// Connects to the websocket at `endpoint` as is, e.g. a self-hosted Realtime server.
+    pub fn with_endpoint(endpoint: &str, key: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            key: key.to_string(),
+            schema: "public".to_string(),
+            heartbeat: DEFAULT_HEARTBEAT,
+            join_timeout: DEFAULT_JOIN_TIMEOUT,
+        }
+    }
+
+    /// Postgres schema of the subscribed tables, `public` by default.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = schema.to_string();
+        self
+    }
+
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = interval;
+        self
+    }
+
+    pub fn join_timeout(mut self, timeout: Duration) -> Self {
+        self.join_timeout = timeout;
+        self
+    }
+
+    /// Streams the changes of the table of `E`.
+    pub async fn subscribe<C, E>(&self) -> Result<Subscription<E>>
+    where
+        C: Client,
+        E: ExtendedCrud<C> + TryFromError<serde_json::Value, serde_json::Error>,
+    {
+        self.subscribe_table(E::TABLE_NAME).await
+    }
+
+    /// Streams the changes of `table`, whose rows are deserialized into `T`.
+    ///
+    /// Fails when the connection or the join of the channel fails or is not answered
+    /// within the join timeout. Once joined, rows failing to deserialize are yielded as
+    /// errors and the stream goes on. A connection error, an unanswered heartbeat or a
+    /// channel error is yielded as a last error, after which the stream ends, as it does
+    /// when the server closes the connection; subscribe again to resume.
+    pub async fn subscribe_table<T>(&self, table: &str) -> Result<Subscription<T>>
+    where
+        T: TryFromError<serde_json::Value, serde_json::Error> + Send + 'static,
+    {
+        let tag = "RealtimeClient.subscribe failed";
+        let url = format!(
+            "{}{}apikey={}&vsn=1.0.0",
+            self.endpoint,
+            if self.endpoint.contains('?') {
+                "&"
+            } else {
+                "?"
+            },
+            self.key
+        );
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .context(tag)?;
+
+        let topic = format!("realtime:{}:{}", self.schema, table);
+        let join = json!({
+            "topic": topic,
+            "event": "phx_join",
+            "payload": {
+                "config": {
+                    "postgres_changes": [
+                        { "event": "*", "schema": self.schema, "table": table }
+                    ]
+                },
+                "access_token": self.key,
+            },
+            "ref": "1",
+        });
+        socket
+            .send(Message::Text(join.to_string()))
+            .await
+            .context(tag)?;
+        tokio::time::timeout(self.join_timeout, joined(&mut socket, &topic))
+            .await
+            .map_err(|_| anyhow!("join not answered within {:?}", self.join_timeout))
+            .context(tag)?
+            .context(tag)?;
+
+        let (sender, receiver) = mpsc::channel(64);
+        let task = tokio::spawn(listen(socket, topic, self.heartbeat, sender));
+        Ok(Subscription { receiver, task })
+    }
+}
+
+/// The stream of changes returned by `RealtimeClient::subscribe`, closing the connection
+/// when dropped.
+pub struct Subscription<T> {
+    receiver: mpsc::Receiver<Result<Change<T>>>,
+    task: JoinHandle<()>,
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = Result<Change<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PhoenixMessage {
+    topic: String,
+    event: String,
+    #[serde(default)]
+    payload: serde_json::Value,
+    #[serde(default, rename = "ref")]
+    reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeData {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    record: serde_json::Value,
+    #[serde(default)]
+    old_record: serde_json::Value,
+}
+
+/// Waits for the reply to the join message of `topic`.
+async fn joined(socket: &mut Socket, topic: &str) -> Result<()> {
+    while let Some(message) = socket.next().await {
+        let Message::Text(text) = message? else {
+            continue;
+        };
+        let message = serde_json::from_str::<PhoenixMessage>(&text)?;
+        if message.topic != topic
+            || message.event != "phx_reply"
+            || message.reference.as_deref() != Some("1")
+        {
+            continue;
+        }
+        return match message.payload.get("status").and_then(|s| s.as_str()) {
+            Some("ok") => Ok(()),
+            _ => Err(anyhow!("join rejected: {}", message.payload)),
+        };
+    }
+    bail!("connection closed before the join reply")
+}
+
+fn change<T>(data: ChangeData) -> Result<Change<T>>
+where
+    T: TryFromError<serde_json::Value, serde_json::Error>,
+{
+    Ok(match data.kind.as_str() {
+        "INSERT" => Change::Insert(T::try_from_err(data.record)?),
+        "UPDATE" => Change::Update {
+            old: data.old_record,
+            new: T::try_from_err(data.record)?,
+        },
+        "DELETE" => Change::Delete {
+            old: data.old_record,
+        },
+        kind => bail!("unknown change type: {}", kind),
+    })
+}
+
+/// Forwards the changes received on `socket` to `sender` and sends heartbeats, until
+/// either side is closed or the connection fails, which is forwarded as a last error.
+async fn listen<T>(
+    mut socket: Socket,
+    topic: String,
+    heartbeat: Duration,
+    sender: mpsc::Sender<Result<Change<T>>>,
+) where
+    T: TryFromError<serde_json::Value, serde_json::Error>,
+{
+    let failure = tokio::select! {
+        failure = receive(&mut socket, &topic, heartbeat, &sender) => failure,
+        _ = sender.closed() => None,
+    };
+    if let Some(e) = failure {
+        warn!("RealtimeClient.listen failed on {}: {:#}", topic, e);
+        let _ = sender.send(Err(e)).await;
+    }
+    let _ = socket.close(None).await;
+}
+
+/// Receives the changes of `topic` until the connection is closed, returning why it
+/// failed otherwise.
+async fn receive<T>(
+    socket: &mut Socket,
+    topic: &str,
+    heartbeat: Duration,
+    sender: &mpsc::Sender<Result<Change<T>>>,
+) -> Option<anyhow::Error>
+where
+    T: TryFromError<serde_json::Value, serde_json::Error>,
+{
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat, heartbeat);
+    let mut reference = 1u64;
+    // Reference of the last heartbeat, until the server answers it.
+    let mut unanswered: Option<String> = None;
+    loop {
+        tokio::select! {
+            message = socket.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return None,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Some(anyhow!(e).context("connection failed")),
+                };
+                let message = match serde_json::from_str::<PhoenixMessage>(&text) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!("RealtimeClient.listen, invalid message on {}: {:#}", topic, e);
+                        continue;
+                    }
+                };
+                if message.topic == "phoenix" && message.event == "phx_reply" {
+                    if message.reference.is_some() && message.reference == unanswered {
+                        unanswered = None;
+                    }
+                    continue;
+                }
+                if message.topic != topic {
+                    debug!("RealtimeClient.listen, {} on {}", message.event, message.topic);
+                    continue;
+                }
+                match message.event.as_str() {
+                    "postgres_changes" => {}
+                    "phx_error" | "phx_close" => {
+                        return Some(anyhow!("channel {}: {}", message.event, message.payload));
+                    }
+                    event => {
+                        debug!("RealtimeClient.listen, {} on {}", event, topic);
+                        continue;
+                    }
+                }
+                let change = serde_json::from_value::<ChangeData>(
+                    message.payload.get("data").cloned().unwrap_or_default(),
+                )
+                .map_err(anyhow::Error::from)
+                .and_then(change::<T>)
+                .context("invalid change");
+                if sender.send(change).await.is_err() {
+                    return None;
+                }
+            }
+            _ = ticker.tick() => {
+                if let Some(unanswered) = &unanswered {
+                    return Some(anyhow!(
+                        "heartbeat {} not answered within {:?}",
+                        unanswered,
+                        heartbeat
+                    ));
+                }
+                reference += 1;
+                let heartbeat = json!({
+                    "topic": "phoenix",
+                    "event": "heartbeat",
+                    "payload": {},
+                    "ref": reference.to_string(),
+                });
+                if let Err(e) = socket.send(Message::Text(heartbeat.to_string())).await {
+                    return Some(anyhow!(e).context("heartbeat failed"));
+                }
+                unanswered = Some(reference.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use tokio::net::TcpListener;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    impl TryFromError<serde_json::Value, serde_json::Error> for User {
+        fn try_from_err(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+            serde_json::from_value(value)
+        }
+    }
+
+    fn postgres_changes(topic: &str, data: serde_json::Value) -> Message {
+        Message::Text(
+            json!({
+                "topic": topic,
+                "event": "postgres_changes",
+                "payload": { "data": data, "ids": [1] },
+                "ref": null,
+            })
+            .to_string(),
+        )
+    }
+
+    /// Accepts one connection and reads its join message.
+    async fn accept(listener: TcpListener) -> (Socket, serde_json::Value) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let stream = MaybeTlsStream::Plain(stream);
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let Some(Ok(Message::Text(join))) = socket.next().await else {
+            panic!("expected a join message");
+        };
+        (socket, serde_json::from_str(&join).unwrap())
+    }
+
+    async fn reply_ok(socket: &mut Socket, join: &serde_json::Value) {
+        let reply = json!({
+            "topic": join["topic"],
+            "event": "phx_reply",
+            "payload": { "status": "ok", "response": { "postgres_changes": [] } },
+            "ref": "1",
+        });
+        socket.send(Message::Text(reply.to_string())).await.unwrap();
+    }
+
+    /// Accepts one connection, answers the join and sends the changes of `users`.
+    async fn fake_server(listener: TcpListener) -> serde_json::Value {
+        let (mut socket, join) = accept(listener).await;
+        reply_ok(&mut socket, &join).await;
+        let topic = join["topic"].as_str().unwrap();
+        for data in [
+            json!({ "type": "INSERT", "record": { "id": 1, "name": "john" }, "old_record": {} }),
+            json!({ "type": "INSERT", "record": { "id": "invalid" } }),
+            json!({
+                "type": "UPDATE",
+                "record": { "id": 1, "name": "jane" },
+                "old_record": { "id": 1 }
+            }),
+            json!({ "type": "DELETE", "record": {}, "old_record": { "id": 1 } }),
+        ] {
+            socket.send(postgres_changes(topic, data)).await.unwrap();
+        }
+        socket.close(None).await.unwrap();
+        join
+    }
+
+    #[tokio::test]
+    async fn streams_changes_from_the_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}/socket", listener.local_addr().unwrap());
+        let server = tokio::spawn(fake_server(listener));
+
+        let subscription = RealtimeClient::with_endpoint(&endpoint, "anon")
+            .subscribe_table::<User>("users")
+            .await
+            .unwrap();
+        let (changes, errors): (Vec<_>, Vec<_>) = subscription
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .partition(Result::is_ok);
+
+        let join = server.await.unwrap();
+        assert_eq!(join["event"], "phx_join");
+        assert_eq!(
+            join["payload"]["config"]["postgres_changes"][0],
+            json!({ "event": "*", "schema": "public", "table": "users" })
+        );
+        assert_eq!(
+            changes.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            [
+                Change::Insert(User {
+                    id: 1,
+                    name: "john".to_string()
+                }),
+                Change::Update {
+                    old: json!({ "id": 1 }),
+                    new: User {
+                        id: 1,
+                        name: "jane".to_string()
+                    }
+                },
+                Change::Delete {
+                    old: json!({ "id": 1 })
+                },
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(format!("{:#}", errors[0].as_ref().unwrap_err()).contains("invalid change"));
+    }
+
+    #[tokio::test]
+    async fn fails_on_unanswered_joins_and_heartbeats() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}/socket", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (socket, _) = accept(listener).await;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(socket);
+        });
+        let error = RealtimeClient::with_endpoint(&endpoint, "anon")
+            .join_timeout(Duration::from_millis(50))
+            .subscribe_table::<User>("users")
+            .await
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", error).contains("join not answered"));
+        server.abort();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}/socket", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, join) = accept(listener).await;
+            reply_ok(&mut socket, &join).await;
+            // Reads the heartbeats without answering them.
+            while socket.next().await.is_some() {}
+        });
+        let mut subscription = RealtimeClient::with_endpoint(&endpoint, "anon")
+            .heartbeat(Duration::from_millis(20))
+            .subscribe_table::<User>("users")
+            .await
+            .unwrap();
+        let error = subscription.next().await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("not answered"));
+        assert!(subscription.next().await.is_none());
+        server.abort();
+    }
+}
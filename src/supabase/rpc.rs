@@ -0,0 +1,135 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::debug;
+
+use crate::supabase::supabase::{check_status, SupabaseClient};
+
+/// HTTP method used to call a Postgres function.
+/// `Get` is only allowed by PostgREST for `STABLE` or `IMMUTABLE` functions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RpcMethod {
+    Get,
+    #[default]
+    Post,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcOptions {
+    pub method: RpcMethod,
+    /// Sends `Prefer: params=single-object`, passing the whole body as one JSON argument.
+    /// Only sent with `RpcMethod::Post`, as GET calls have no body.
+    pub single_object: bool,
+}
+
+impl RpcOptions {
+    pub fn get() -> Self {
+        Self {
+            method: RpcMethod::Get,
+            ..Default::default()
+        }
+    }
+
+    pub fn single_object(mut self) -> Self {
+        self.single_object = true;
+        self
+    }
+}
+
+impl SupabaseClient {
+    /// Calls the Postgres function `name` with `args` using POST.
+    pub async fn rpc<A, R>(&self, name: &str, args: &A) -> Result<R>
+    where
+        A: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        self.rpc_with(name, args, RpcOptions::default()).await
+    }
+
+    pub async fn rpc_with<A, R>(&self, name: &str, args: &A, options: RpcOptions) -> Result<R>
+    where
+        A: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let tag = "SupabaseClient.rpc";
+        debug!("{}, function: {}, options: {:?}", tag, name, options);
+        let args = serde_json::to_value(args).map_err(|e| anyhow!(e).context(tag))?;
+
+        let mut request = match options.method {
+            RpcMethod::Post => self.function(name).rpc(args.to_string()).build(),
+            RpcMethod::Get => self.function(name).build().query(&query_params(&args)?),
+        };
+        if options.single_object && options.method == RpcMethod::Post {
+            request = request.header("Prefer", "params=single-object");
+        }
+
+        let response = request.send().await.map_err(|e| anyhow!(e).context(tag))?;
+        check_status(tag, &response)?;
+        let text = response.text().await.map_err(|e| anyhow!(e).context(tag))?;
+        if text.is_empty() {
+            return serde_json::from_value(serde_json::Value::Null)
+                .map_err(|e| anyhow!(e).context(tag));
+        }
+        serde_json::from_str(&text).map_err(|e| anyhow!(e).context(tag))
+    }
+}
+
+fn query_params(args: &serde_json::Value) -> Result<Vec<(String, String)>> {
+    match args {
+        serde_json::Value::Null => Ok(vec![]),
+        serde_json::Value::Object(map) => Ok(map
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                };
+                (name.clone(), value)
+            })
+            .collect()),
+        _ => bail!("SupabaseClient.rpc, GET arguments must be an object"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supabase::supabase::tests::{body, header, serve};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn posts_arguments_as_the_body() {
+        let (url, server) = serve(200, "3").await;
+        let client = SupabaseClient::builder(&url, "key").build().unwrap();
+        let sum: i64 = client.rpc("add", &json!({ "a": 1, "b": 2 })).await.unwrap();
+        assert_eq!(sum, 3);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /rest/v1/rpc/add "));
+        assert_eq!(body(&request), r#"{"a":1,"b":2}"#);
+        assert_eq!(header(&request, "prefer"), None);
+    }
+
+    #[tokio::test]
+    async fn prefers_single_objects_only_on_post() {
+        let (url, server) = serve(200, "3").await;
+        let client = SupabaseClient::builder(&url, "key").build().unwrap();
+        let options = RpcOptions::default().single_object();
+        let _: i64 = client
+            .rpc_with("add", &json!({ "a": 1, "b": 2 }), options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert_eq!(header(&request, "prefer"), Some("params=single-object"));
+
+        let (url, server) = serve(200, "3").await;
+        let client = SupabaseClient::builder(&url, "key").build().unwrap();
+        let options = RpcOptions::get().single_object();
+        let _: i64 = client
+            .rpc_with("add", &json!({ "a": 1, "b": "two" }), options)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /rest/v1/rpc/add?a=1&b=two "));
+        assert_eq!(header(&request, "prefer"), None);
+        assert_eq!(body(&request), "");
+    }
+}
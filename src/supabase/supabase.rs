@@ -1,47 +1,213 @@
 use crate::clients::client::Client;
-use anyhow::{anyhow, bail, Result};
+use crate::clients::error::{Conflict, StatusError};
+use crate::clients::transaction::Operation;
+use crate::entity::column::FilterOp;
+use crate::entity::row::JsonRow;
+use crate::supabase::builder::SupabaseClientBuilder;
+use crate::telemetry::{crud_span, instrumented};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use log::debug;
-use postgrest::Postgrest;
-use serde::Serialize;
+use postgrest::{Builder, Postgrest};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{de::DeserializeOwned, Serialize};
 
+/// Postgres function applying a batch of `Operation`s in one transaction,
+/// see `supabase/migrations` for its definition.
+pub const BATCH_FUNCTION: &str = "ext_crud_execute_batch";
+
+#[derive(Clone)]
 pub struct SupabaseClient {
+    /// Raw PostgREST client sharing the endpoint, schema and headers, but not the HTTP
+    /// settings of the builder.
     pub postgrest: Postgrest,
+    endpoint: String,
+    schema: Option<String>,
+    headers: HeaderMap,
+    http: reqwest::Client,
 }
 
 impl SupabaseClient {
+    /// Panics when `key` is not a valid header value, e.g. when it ends with a newline.
+    #[deprecated(note = "panics on invalid keys, use `SupabaseClient::builder(url, key).build()`")]
     pub fn new(url: &str, key: &str) -> Self {
-        let postgrest = Self::new_postgrest(url, key);
-        Self { postgrest }
+        Self::builder(url, key)
+            .build()
+            .expect("SupabaseClient.new, Invalid url or key")
+    }
+
+    pub fn builder(url: &str, key: &str) -> SupabaseClientBuilder {
+        SupabaseClientBuilder::new(url, key)
+    }
+
+    pub(crate) fn from_parts(
+        endpoint: String,
+        schema: Option<String>,
+        headers: HeaderMap,
+        http: reqwest::Client,
+    ) -> Result<Self> {
+        let mut postgrest = Postgrest::new(endpoint.clone());
+        if let Some(schema) = &schema {
+            postgrest = postgrest.schema(schema);
+        }
+        // `Postgrest` only takes text headers, which opaque bytes cannot be turned into.
+        for (name, value) in &headers {
+            let value = value
+                .to_str()
+                .map_err(|e| anyhow!(e).context(format!("Header {} is not text", name)))?;
+            postgrest = postgrest.insert_header(name, value);
+        }
+        Ok(Self {
+            postgrest,
+            endpoint,
+            schema,
+            headers,
+            http,
+        })
+    }
+
+    /// Returns a client authenticating as the end user owning `access_token`, so row level
+    /// security policies apply. The `apikey` header and the connection pool are shared.
+    pub fn with_user_token(&self, access_token: &str) -> Result<Self> {
+        let tag = "SupabaseClient.with_user_token";
+        let authorization = HeaderValue::from_str(&format!("Bearer {}", access_token))
+            .map_err(|e| anyhow!(e).context(tag))?;
+        let mut client = self.clone();
+        client.postgrest = client
+            .postgrest
+            .insert_header("Authorization", authorization.to_str()?);
+        client.headers.insert("Authorization", authorization);
+        Ok(client)
+    }
+
+    /// Starts a request on `table` with the configured schema, headers and HTTP client.
+    pub(crate) fn table(&self, table: &str) -> Builder {
+        Builder::new(
+            format!("{}/{}", self.endpoint, table),
+            self.schema.clone(),
+            self.headers.clone(),
+            self.http.clone(),
+        )
     }
 
-    pub(crate) fn new_postgrest(url: &str, key: &str) -> Postgrest {
-        let endpoint = format!("{}/rest/v1/", url);
-        Postgrest::new(endpoint)
-            .insert_header("apikey", key)
-            .insert_header("Authorization", format!("Bearer {}", key))
+    /// Starts a request on the Postgres function `name`.
+    pub(crate) fn function(&self, name: &str) -> Builder {
+        self.table(&format!("rpc/{}", name))
+    }
+
+    /// Reads the rows of `find_where`, deserialized straight from the response into `T`.
+    async fn select_where<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<T>> {
+        let span = crud_span!("SupabaseClient.find_where", table, "read", filters.len());
+        instrumented(span, async move {
+            let tag = "SupabaseClient.find_where";
+            if limit == Some(0) {
+                return Ok(vec![]);
+            }
+
+            let mut query = self.table(table).select("*");
+            for (column, op, value) in filters {
+                query = match op {
+                    // `eq.null` compares with the string "null" instead of testing for NULL.
+                    FilterOp::Eq if value.is_null() => query.is(*column, "null"),
+                    FilterOp::Neq if value.is_null() => query.not("is", *column, "null"),
+                    FilterOp::Eq => query.eq(*column, filter_value(value)),
+                    FilterOp::Neq => query.neq(*column, filter_value(value)),
+                    FilterOp::Gt => query.gt(*column, filter_value(value)),
+                    FilterOp::Gte => query.gte(*column, filter_value(value)),
+                    FilterOp::Lt => query.lt(*column, filter_value(value)),
+                    FilterOp::Lte => query.lte(*column, filter_value(value)),
+                    FilterOp::In => {
+                        let values = value
+                            .as_array()
+                            .ok_or_else(|| anyhow!("{}, `in` expects an array", tag))?
+                            .iter()
+                            .map(serde_json::Value::to_string);
+                        query.in_(*column, values)
+                    }
+                    FilterOp::IsNull => query.is(*column, "null"),
+                    FilterOp::NotNull => query.not("is", *column, "null"),
+                };
+            }
+            if !order.is_empty() {
+                let order = order
+                    .iter()
+                    .map(|(column, ascending)| {
+                        format!("{}.{}", column, if *ascending { "asc" } else { "desc" })
+                    })
+                    .collect::<Vec<_>>();
+                query = query.order(order.join(","));
+            }
+            if let Some(limit) = limit {
+                query = query.limit(limit);
+            }
+            let response = query.execute().await.map_err(|e| anyhow!(e).context(tag))?;
+            check_status(tag, &response)?;
+            let text = response.text().await.map_err(|e| anyhow!(e).context(tag))?;
+            let data = serde_json::from_str(&text).map_err(|e| anyhow!(e).context(tag))?;
+            Ok(data)
+        })
+        .await
+    }
+
+    /// Reads the rows of `find_range`, deserialized straight from the response into `T`.
+    async fn select_range<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<T>> {
+        let span = crud_span!("SupabaseClient.find_range", table, "read", limit);
+        instrumented(span, async move {
+            let tag = "SupabaseClient.find_range";
+            if limit == 0 {
+                return Ok(vec![]);
+            }
+
+            // Keyset paging: each page is an index range scan, however deep it is.
+            let mut query = self
+                .table(table)
+                .select("*")
+                .order(format!("{}.asc", key))
+                .limit(limit);
+            if let Some(after) = after {
+                query = query.gt(key, filter_value(after));
+            }
+            let response = query.execute().await.map_err(|e| anyhow!(e).context(tag))?;
+            check_status(tag, &response)?;
+            let text = response.text().await.map_err(|e| anyhow!(e).context(tag))?;
+            let data = serde_json::from_str(&text).map_err(|e| anyhow!(e).context(tag))?;
+            Ok(data)
+        })
+        .await
     }
 }
 
 #[async_trait]
 impl Client for SupabaseClient {
+    type Row = JsonRow;
+
     async fn create<T: Serialize + Send + Sync>(&self, table: &str, item: &T) -> Result<()> {
-        let tag = "SupabaseClient.create";
-        debug!("SupabaseClient.create: {}, table: {}", tag, table);
-        let s = serde_json::to_string(item).map_err(|e| anyhow!(e).context(tag))?;
-        let client = self.postgrest.clone();
-
-        let response = client
-            .from(table)
-            .insert(s)
-            .execute()
-            .await
-            .map_err(|e| anyhow!(e).context(tag))?;
+        let span = crud_span!("SupabaseClient.create", table, "create", 1);
+        instrumented(span, async move {
+            let tag = "SupabaseClient.create";
+            let s = serde_json::to_string(item).map_err(|e| anyhow!(e).context(tag))?;
+            let response = self
+                .table(table)
+                .insert(s)
+                .execute()
+                .await
+                .map_err(|e| anyhow!(e).context(tag))?;
 
-        if !response.status().is_success() {
-            bail!("{}, Request failed with status: {}", tag, response.status())
-        }
-        Ok(())
+            check_status(tag, &response)?;
+            Ok(())
+        })
+        .await
     }
 
     async fn find_by_keys<K: Serialize + Send + Sync>(
@@ -50,24 +216,120 @@ impl Client for SupabaseClient {
         key: &str,
         ids: Vec<K>,
     ) -> Result<Vec<serde_json::Value>> {
-        let tag = "SupabaseClient.find_by_keys";
-
-        let client = self.postgrest.clone();
-        let ids = ids
-            .iter()
-            .map(|id| serde_json::to_string(id).map_err(|e| anyhow!(e).context(tag)))
-            .collect::<Result<Vec<String>>>()?;
-        let response = client.from(table).in_(key, &ids).execute().await?;
-        if !response.status().is_success() {
-            bail!(format!(
-                "{}, Request failed with status: {}",
-                tag,
-                response.status()
-            ));
-        }
-        let text = response.text().await.map_err(|e| anyhow!(e).context(tag))?;
-        let data = serde_json::from_str(&text).map_err(|e| anyhow!(e).context(tag))?;
-        Ok(data)
+        self.find_by_keys_select(table, key, ids, &["*"]).await
+    }
+
+    async fn find_by_keys_select<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+        columns: &[&str],
+    ) -> Result<Vec<serde_json::Value>> {
+        let span = crud_span!("SupabaseClient.find_by_keys", table, "read", ids.len());
+        instrumented(span, async move {
+            let tag = "SupabaseClient.find_by_keys_select";
+
+            let ids = ids
+                .iter()
+                .map(|id| serde_json::to_string(id).map_err(|e| anyhow!(e).context(tag)))
+                .collect::<Result<Vec<String>>>()?;
+            let response = self
+                .table(table)
+                .select(columns.join(","))
+                .in_(key, &ids)
+                .execute()
+                .await
+                .map_err(|e| anyhow!(e).context(tag))?;
+            check_status(tag, &response)?;
+            let text = response.text().await.map_err(|e| anyhow!(e).context(tag))?;
+            let data = serde_json::from_str(&text).map_err(|e| anyhow!(e).context(tag))?;
+            Ok(data)
+        })
+        .await
+    }
+
+    /// Keeps every row as unparsed JSON text, so entities are deserialized from the
+    /// response without an intermediate `serde_json::Value`.
+    async fn find_rows<K: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<Self::Row>> {
+        self.find_by_keys_typed(table, key, ids).await
+    }
+
+    async fn find_by_keys_typed<K: Serialize + Send + Sync, T: DeserializeOwned + Send>(
+        &self,
+        table: &str,
+        key: &str,
+        ids: Vec<K>,
+    ) -> Result<Vec<T>> {
+        let span = crud_span!("SupabaseClient.find_by_keys", table, "read", ids.len());
+        instrumented(span, async move {
+            let tag = "SupabaseClient.find_by_keys_typed";
+
+            let ids = ids
+                .iter()
+                .map(|id| serde_json::to_string(id).map_err(|e| anyhow!(e).context(tag)))
+                .collect::<Result<Vec<String>>>()?;
+            let response = self
+                .table(table)
+                .select("*")
+                .in_(key, &ids)
+                .execute()
+                .await
+                .map_err(|e| anyhow!(e).context(tag))?;
+            check_status(tag, &response)?;
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| anyhow!(e).context(tag))?;
+            let data = serde_json::from_slice(&body).map_err(|e| anyhow!(e).context(tag))?;
+            Ok(data)
+        })
+        .await
+    }
+
+    async fn find_where(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.select_where(table, filters, order, limit).await
+    }
+
+    async fn find_where_rows(
+        &self,
+        table: &str,
+        filters: &[(&str, FilterOp, serde_json::Value)],
+        order: &[(&str, bool)],
+        limit: Option<usize>,
+    ) -> Result<Vec<Self::Row>> {
+        self.select_where(table, filters, order, limit).await
+    }
+
+    async fn find_range(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.select_range(table, key, after, limit).await
+    }
+
+    async fn find_range_rows(
+        &self,
+        table: &str,
+        key: &str,
+        after: Option<&serde_json::Value>,
+        limit: usize,
+    ) -> Result<Vec<Self::Row>> {
+        self.select_range(table, key, after, limit).await
     }
 
     async fn update_by_keys<K: Serialize + Send + Sync, T: Serialize + Send + Sync>(
@@ -79,26 +341,27 @@ impl Client for SupabaseClient {
     where
         K: ToString + AsRef<str>,
     {
-        let tag = "SupabaseClient.update_by_keys";
-
-        let client = self.postgrest.clone();
-        for item in items {
-            println!("{}", serde_json::to_string(&item.1).unwrap());
-            let mut query = client
-                .from(table)
-                .update(serde_json::to_string(&item.1).map_err(|e| anyhow!(e).context(tag))?);
-            query = query.eq(key, item.0);
-
-            let response = query.execute().await?;
-            if !response.status().is_success() {
-                bail!(format!(
-                    "{}, Request failed with status: {}",
-                    tag,
-                    response.status()
-                ));
+        let span = crud_span!(
+            "SupabaseClient.update_by_keys",
+            table,
+            "update",
+            items.len()
+        );
+        instrumented(span, async move {
+            let tag = "SupabaseClient.update_by_keys";
+
+            for item in items {
+                let mut query = self
+                    .table(table)
+                    .update(serde_json::to_string(&item.1).map_err(|e| anyhow!(e).context(tag))?);
+                query = query.eq(key, item.0);
+
+                let response = query.execute().await.map_err(|e| anyhow!(e).context(tag))?;
+                check_status(tag, &response)?;
             }
-        }
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn delete_by_keys<K: Serialize + Send + Sync>(
@@ -107,22 +370,275 @@ impl Client for SupabaseClient {
         key: &str,
         ids: Vec<K>,
     ) -> Result<()> {
-        let tag = "SupabaseClient.delete_by_keys";
-
-        let client = self.postgrest.clone();
-        for id in ids {
-            let id = serde_json::to_string(&id).map_err(|e| anyhow!(e).context(tag))?;
-            let mut query = client.from(table).delete();
-            query = query.eq(key, id);
-            let response = query.execute().await?;
-            if !response.status().is_success() {
-                bail!(format!(
-                    "{}, Request failed with status: {}",
-                    tag,
-                    response.status()
-                ));
+        let span = crud_span!("SupabaseClient.delete_by_keys", table, "delete", ids.len());
+        instrumented(span, async move {
+            let tag = "SupabaseClient.delete_by_keys";
+
+            for id in ids {
+                let id = serde_json::to_string(&id).map_err(|e| anyhow!(e).context(tag))?;
+                let mut query = self.table(table).delete();
+                query = query.eq(key, id);
+                let response = query.execute().await.map_err(|e| anyhow!(e).context(tag))?;
+                check_status(tag, &response)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_where<T: Serialize + Send + Sync>(
+        &self,
+        table: &str,
+        filters: &[(&str, String)],
+        item: &T,
+    ) -> Result<usize> {
+        let span = crud_span!("SupabaseClient.update_where", table, "update", 1);
+        instrumented(span, async move {
+            let tag = "SupabaseClient.update_where";
+
+            let mut query = self
+                .table(table)
+                .update(serde_json::to_string(item).map_err(|e| anyhow!(e).context(tag))?);
+            for (column, value) in filters {
+                query = query.eq(*column, value);
+            }
+            let response = query.execute().await.map_err(|e| anyhow!(e).context(tag))?;
+            check_status(tag, &response)?;
+            let text = response.text().await.map_err(|e| anyhow!(e).context(tag))?;
+            let rows: Vec<serde_json::Value> =
+                serde_json::from_str(&text).map_err(|e| anyhow!(e).context(tag))?;
+            Ok(rows.len())
+        })
+        .await
+    }
+
+    async fn execute_batch(&self, operations: Vec<Operation>) -> Result<()> {
+        let span = crud_span!(
+            "SupabaseClient.execute_batch",
+            BATCH_FUNCTION,
+            "batch",
+            operations.len()
+        );
+        instrumented(span, async move {
+            let tag = "SupabaseClient.execute_batch";
+            let body = serde_json::json!({ "operations": operations }).to_string();
+            let response = self
+                .function(BATCH_FUNCTION)
+                .rpc(body)
+                .execute()
+                .await
+                .map_err(|e| anyhow!(e).context(tag))?;
+            if response.status() != reqwest::StatusCode::CONFLICT {
+                return check_status(tag, &response);
+            }
+            let error = check_status(tag, &response).unwrap_err();
+            // The batch function answers 409 when an expected version no longer matches,
+            // naming the index of the operation in the details.
+            let text = response.text().await.unwrap_or_default();
+            match conflicting_operation(&operations, &text) {
+                Some(conflict) => Err(error.context(conflict)),
+                None => Err(error),
+            }
+        })
+        .await
+    }
+}
+
+/// The `Conflict` of the versioned update named by the details of a 409 response of
+/// `BATCH_FUNCTION`, e.g. `{"details": "{\"operation\": 2}"}`.
+fn conflicting_operation(operations: &[Operation], response: &str) -> Option<Conflict> {
+    let response: serde_json::Value = serde_json::from_str(response).ok()?;
+    let details: serde_json::Value = serde_json::from_str(response["details"].as_str()?).ok()?;
+    let index = usize::try_from(details["operation"].as_u64()?).ok()?;
+    match operations.get(index)? {
+        Operation::Update {
+            table,
+            id,
+            expected_version: Some(expected),
+            ..
+        } => Some(Conflict {
+            table: table.clone(),
+            id: filter_value(id),
+            version: expected.version,
+        }),
+        _ => None,
+    }
+}
+
+/// A filter value as PostgREST expects it in the query string, strings unquoted.
+fn filter_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+pub(crate) fn check_status(tag: &str, response: &reqwest::Response) -> Result<()> {
+    if response.status().is_success() {
+        return Ok(());
+    }
+    Err(
+        anyhow::Error::new(StatusError::from_response(response)).context(format!(
+            "{}, Request failed with status: {}",
+            tag,
+            response.status()
+        )),
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::clients::transaction::ExpectedVersion;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Answers one request with `status` and `body`, returning the request as received.
+    pub(crate) async fn serve(status: u16, body: &str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 4096];
+            loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
             }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, server)
+    }
+
+    /// The value of the header `name` of `request`.
+    pub(crate) fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.split("\r\n\r\n").next()?.lines().find_map(|line| {
+            let (header, value) = line.split_once(':')?;
+            header.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// The body of `request`.
+    pub(crate) fn body(request: &str) -> &str {
+        request.split_once("\r\n\r\n").map_or("", |(_, body)| body)
+    }
+
+    fn update(id: serde_json::Value, version: Option<i64>) -> Operation {
+        Operation::Update {
+            table: "products".to_string(),
+            key: "product_id".to_string(),
+            id,
+            item: json!({ "name": "widget" }),
+            expected_version: version.map(|version| ExpectedVersion {
+                column: "version".to_string(),
+                version,
+            }),
         }
-        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_the_conflict_of_the_failed_operation() {
+        let body = json!({
+            "code": "PT409",
+            "message": "Conflict on products b, version 3 is outdated",
+            "details": "{\"operation\": 1}",
+        });
+        let (url, server) = serve(409, &body.to_string()).await;
+        let client = SupabaseClient::builder(&url, "key").build().unwrap();
+        let operations = vec![update(json!("a"), Some(1)), update(json!("b"), Some(3))];
+
+        let error = client.execute_batch(operations).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Conflict>(),
+            Some(&Conflict {
+                table: "products".to_string(),
+                id: "b".to_string(),
+                version: 3,
+            })
+        );
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /rest/v1/rpc/ext_crud_execute_batch "));
+
+        let (url, _server) = serve(409, "{}").await;
+        let client = SupabaseClient::builder(&url, "key").build().unwrap();
+        let operations = vec![update(json!("a"), Some(1)), update(json!("b"), None)];
+        let error = client.execute_batch(operations).await.unwrap_err();
+        assert!(error.downcast_ref::<Conflict>().is_none());
+        assert_eq!(error.downcast_ref::<StatusError>().unwrap().status, 409);
+    }
+
+    #[tokio::test]
+    async fn sends_the_schema_and_custom_headers() {
+        let builder = |url: &str| {
+            SupabaseClient::builder(url, "key")
+                .schema("api")
+                .header("x-tenant", "acme")
+                .build()
+                .unwrap()
+        };
+        let (url, server) = serve(200, "[]").await;
+        builder(&url)
+            .find_by_keys("users", "id", vec![1])
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /rest/v1/users?"));
+        assert_eq!(header(&request, "accept-profile"), Some("api"));
+        assert_eq!(header(&request, "x-tenant"), Some("acme"));
+        assert_eq!(header(&request, "apikey"), Some("key"));
+        assert_eq!(header(&request, "authorization"), Some("Bearer key"));
+
+        let (url, server) = serve(201, "").await;
+        builder(&url)
+            .create("users", &json!({ "id": 1 }))
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /rest/v1/users "));
+        assert_eq!(header(&request, "content-profile"), Some("api"));
+        assert_eq!(header(&request, "x-tenant"), Some("acme"));
+        assert_eq!(body(&request), r#"{"id":1}"#);
+    }
+
+    #[tokio::test]
+    async fn authenticates_with_user_tokens() {
+        let (url, server) = serve(200, "[]").await;
+        let client = SupabaseClient::builder(&url, "key").build().unwrap();
+        client
+            .with_user_token("user-jwt")
+            .unwrap()
+            .find_by_keys("users", "id", vec![1])
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert_eq!(header(&request, "authorization"), Some("Bearer user-jwt"));
+        assert_eq!(header(&request, "apikey"), Some("key"));
+        assert!(client.with_user_token("bad\ntoken").is_err());
     }
 }
@@ -0,0 +1,78 @@
+use std::future::Future;
+use std::time::Instant;
+
+use anyhow::Result;
+use tracing::{Instrument, Span};
+
+/// Replacement for the values of sensitive columns in logged payloads.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Opens the span of a CRUD operation. `latency_ms` and `outcome` are recorded by
+/// `instrumented` when the operation completes.
+macro_rules! crud_span {
+    ($name:literal, $table:expr, $operation:literal, $key_count:expr) => {
+        tracing::info_span!(
+            $name,
+            table = $table,
+            operation = $operation,
+            key_count = $key_count as u64,
+            latency_ms = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        )
+    };
+}
+
+pub(crate) use crud_span;
+
+/// Runs `future` inside `span` and records its latency and outcome on the span.
+pub(crate) async fn instrumented<T, F>(span: Span, future: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    let result = future.instrument(span.clone()).await;
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+    match &result {
+        Ok(_) => {
+            span.record("outcome", "ok");
+        }
+        Err(e) => {
+            span.record("outcome", "error");
+            tracing::debug!(parent: &span, error = %format!("{:#}", e), "operation failed");
+        }
+    }
+    result
+}
+
+/// Logs `payload` at trace level with its `sensitive` columns redacted.
+pub(crate) fn trace_payload<T: serde::Serialize + ?Sized>(payload: &T, sensitive: &[&str]) {
+    if !tracing::enabled!(tracing::Level::TRACE) {
+        return;
+    }
+    if let Ok(value) = serde_json::to_value(payload) {
+        tracing::trace!(payload = %redact(&value, sensitive), "payload");
+    }
+}
+
+/// Returns a copy of `value` with the `fields` of every object replaced by `REDACTED`,
+/// for logging payloads.
+pub fn redact(value: &serde_json::Value, fields: &[&str]) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(row) => serde_json::Value::Object(
+            row.iter()
+                .map(|(column, value)| {
+                    let value = if fields.contains(&column.as_str()) {
+                        serde_json::Value::String(REDACTED.to_string())
+                    } else {
+                        redact(value, fields)
+                    };
+                    (column.clone(), value)
+                })
+                .collect(),
+        ),
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.iter().map(|value| redact(value, fields)).collect())
+        }
+        value => value.clone(),
+    }
+}
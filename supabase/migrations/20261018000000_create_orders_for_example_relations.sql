@@ -0,0 +1,7 @@
+-- For examples/basic_usage.rs running.
+
+CREATE TABLE orders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users (id),
+    quantity INT NOT NULL
+);
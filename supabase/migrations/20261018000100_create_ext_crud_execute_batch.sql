@@ -0,0 +1,41 @@
+-- Applies a batch of ext-crud-rs operations in a single transaction,
+-- called by SupabaseClient::execute_batch.
+
+CREATE OR REPLACE FUNCTION ext_crud_execute_batch(operations JSONB)
+RETURNS VOID
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    operation JSONB;
+    columns TEXT;
+BEGIN
+    FOR operation IN SELECT * FROM jsonb_array_elements(operations)
+    LOOP
+        IF operation ? 'item' THEN
+            SELECT string_agg(format('%I', column_name), ',')
+            INTO columns
+            FROM jsonb_object_keys(operation->'item') AS column_name;
+        END IF;
+
+        CASE operation->>'op'
+        WHEN 'create' THEN
+            EXECUTE format(
+                'INSERT INTO %1$I (%2$s) SELECT %2$s FROM jsonb_populate_record(NULL::%1$I, $1)',
+                operation->>'table', columns
+            ) USING operation->'item';
+        WHEN 'update' THEN
+            EXECUTE format(
+                'UPDATE %1$I SET (%2$s) = (SELECT %2$s FROM jsonb_populate_record(NULL::%1$I, $1)) WHERE %3$I::TEXT = $2',
+                operation->>'table', columns, operation->>'key'
+            ) USING operation->'item', operation->>'id';
+        WHEN 'delete' THEN
+            EXECUTE format(
+                'DELETE FROM %1$I WHERE %2$I::TEXT = $1',
+                operation->>'table', operation->>'key'
+            ) USING operation->>'id';
+        ELSE
+            RAISE EXCEPTION 'unknown operation: %', operation->>'op';
+        END CASE;
+    END LOOP;
+END;
+$$;
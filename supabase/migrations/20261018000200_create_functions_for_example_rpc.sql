@@ -0,0 +1,9 @@
+-- For examples/basic_usage.rs running.
+
+CREATE OR REPLACE FUNCTION count_users_older_than(min_age INT)
+RETURNS BIGINT
+LANGUAGE sql
+STABLE
+AS $$
+    SELECT count(*) FROM users WHERE age > min_age;
+$$;
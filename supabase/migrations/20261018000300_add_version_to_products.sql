@@ -0,0 +1,3 @@
+-- For examples/basic_usage.rs running, optimistic concurrency control on products.
+
+ALTER TABLE products ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- For examples/basic_usage.rs running, timestamps managed by ExtendedCrud.
+
+ALTER TABLE orders
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
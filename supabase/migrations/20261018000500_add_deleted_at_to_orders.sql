@@ -0,0 +1,3 @@
+-- For examples/basic_usage.rs running, soft delete of orders.
+
+ALTER TABLE orders ADD COLUMN deleted_at TIMESTAMPTZ;
@@ -0,0 +1,14 @@
+-- For examples/basic_usage.rs running, change history of users written by AuditingClient.
+
+CREATE TABLE audit_log (
+    audit_id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    "table" TEXT NOT NULL,
+    "key" TEXT NOT NULL,
+    id JSONB NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT,
+    at TIMESTAMPTZ NOT NULL,
+    before JSONB,
+    after JSONB,
+    changes JSONB NOT NULL
+);
@@ -0,0 +1,14 @@
+-- Events written by OutboxClient in the batch of each write, delivered by OutboxRelay.
+
+CREATE TABLE outbox (
+    id UUID PRIMARY KEY,
+    "table" TEXT NOT NULL,
+    "key" JSONB,
+    op TEXT NOT NULL,
+    payload JSONB,
+    created_at TIMESTAMPTZ NOT NULL,
+    dispatched BOOLEAN NOT NULL DEFAULT FALSE,
+    dispatched_at TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_undispatched ON outbox (created_at) WHERE NOT dispatched;
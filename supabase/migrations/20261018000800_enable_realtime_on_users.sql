@@ -0,0 +1,3 @@
+-- For examples/realtime_changes.rs running, publish the changes of users to Realtime.
+
+ALTER PUBLICATION supabase_realtime ADD TABLE users;
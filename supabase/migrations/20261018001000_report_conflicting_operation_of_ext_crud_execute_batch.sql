@@ -0,0 +1,61 @@
+-- Names the failed operation in the details of the 409 response, as its index in the
+-- batch, so that clients report the conflict of the right row.
+
+CREATE OR REPLACE FUNCTION ext_crud_execute_batch(operations JSONB)
+RETURNS VOID
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    operation JSONB;
+    position BIGINT;
+    columns TEXT;
+    updated INTEGER;
+BEGIN
+    FOR operation, position IN SELECT * FROM jsonb_array_elements(operations) WITH ORDINALITY
+    LOOP
+        IF operation ? 'item' THEN
+            SELECT string_agg(format('%I', column_name), ',')
+            INTO columns
+            FROM jsonb_object_keys(operation->'item') AS column_name;
+        END IF;
+
+        CASE operation->>'op'
+        WHEN 'create' THEN
+            EXECUTE format(
+                'INSERT INTO %1$I (%2$s) SELECT %2$s FROM jsonb_populate_record(NULL::%1$I, $1)',
+                operation->>'table', columns
+            ) USING operation->'item';
+        WHEN 'update' THEN
+            IF operation ? 'expected_version' THEN
+                EXECUTE format(
+                    'UPDATE %1$I SET (%2$s) = (SELECT %2$s FROM jsonb_populate_record(NULL::%1$I, $1)) WHERE %3$I::TEXT = $2 AND %4$I = $3',
+                    operation->>'table', columns, operation->>'key',
+                    operation->'expected_version'->>'column'
+                ) USING operation->'item', operation->>'id',
+                    (operation->'expected_version'->>'version')::BIGINT;
+                GET DIAGNOSTICS updated = ROW_COUNT;
+                -- PostgREST answers the PTxyz SQLSTATEs with the HTTP status xyz.
+                IF updated = 0 THEN
+                    RAISE SQLSTATE 'PT409' USING MESSAGE = format(
+                        'Conflict on %s %s, version %s is outdated',
+                        operation->>'table', operation->>'id',
+                        operation->'expected_version'->>'version'
+                    ), DETAIL = jsonb_build_object('operation', position - 1)::TEXT;
+                END IF;
+            ELSE
+                EXECUTE format(
+                    'UPDATE %1$I SET (%2$s) = (SELECT %2$s FROM jsonb_populate_record(NULL::%1$I, $1)) WHERE %3$I::TEXT = $2',
+                    operation->>'table', columns, operation->>'key'
+                ) USING operation->'item', operation->>'id';
+            END IF;
+        WHEN 'delete' THEN
+            EXECUTE format(
+                'DELETE FROM %1$I WHERE %2$I::TEXT = $1',
+                operation->>'table', operation->>'key'
+            ) USING operation->>'id';
+        ELSE
+            RAISE EXCEPTION 'unknown operation: %', operation->>'op';
+        END CASE;
+    END LOOP;
+END;
+$$;